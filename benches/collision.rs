@@ -0,0 +1,89 @@
+// Broad phase benchmark, run with `cargo bench --bench collision`.
+//
+// Pulls the collision modules in by path like the ecs benchmark. It counts
+// overlapping pairs among thousands of boxes scattered over a large field,
+// once by testing every pair and once through the spatial grid.
+// Unit tests in the included modules are compiled in but never run here
+#![allow(dead_code, unused_imports)]
+
+#[path = "../src/broadphase.rs"]
+mod broadphase;
+#[path = "../src/collision.rs"]
+mod collision;
+#[path = "../src/vec2.rs"]
+mod vec2;
+
+use broadphase::SpatialGrid;
+use collision::{RoundedBox, Shape};
+use std::hint::black_box;
+use std::time::Instant;
+use vec2::Vec2;
+
+const BODIES: usize = 5_000;
+const FIELD: f32 = 4_000.0;
+const CELL_SIZE: f32 = 64.0;
+const RUNS: u32 = 20;
+
+// Small LCG so the layout is the same every run without pulling in rand
+fn scatter() -> Vec<(Vec2, Vec2)> {
+    let mut state: u32 = 0x2545_f491;
+    let mut next = move || {
+        state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        (state >> 8) as f32 / (1 << 24) as f32
+    };
+
+    (0..BODIES)
+        .map(|_| {
+            let pos = Vec2::new(next() * FIELD, next() * FIELD);
+            let size = Vec2::new(5.0 + next() * 50.0, 5.0 + next() * 50.0);
+            (pos, size)
+        })
+        .collect()
+}
+
+fn overlaps(a: &(Vec2, Vec2), b: &(Vec2, Vec2)) -> bool {
+    collision::penetration(a.0, RoundedBox::new(Shape::Aabb, a.1), b.0, RoundedBox::new(Shape::Aabb, b.1)).is_some()
+}
+
+fn time<F: FnMut() -> usize>(name: &str, mut f: F) {
+    // Warm up once before timing
+    let pairs = f();
+
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        black_box(f());
+    }
+    let per_run = start.elapsed() / RUNS;
+    let per_body = per_run.as_nanos() as f64 / BODIES as f64;
+
+    println!("{:<40} {:>10.3?} per run {:>8.2} ns/body {:>6} pairs", name, per_run, per_body, pairs);
+}
+
+fn main() {
+    let bodies = scatter();
+
+    time("every pair", || {
+        let mut pairs = 0;
+        for (index, body) in bodies.iter().enumerate() {
+            pairs += bodies[index + 1..].iter().filter(|other| overlaps(body, other)).count();
+        }
+        pairs
+    });
+
+    let mut grid = SpatialGrid::new(CELL_SIZE);
+    let mut found = Vec::new();
+
+    time("spatial grid, rebuilt every run", || {
+        grid.clear();
+        for (index, body) in bodies.iter().enumerate() {
+            grid.insert(index, body.0, body.1);
+        }
+
+        let mut pairs = 0;
+        for (index, body) in bodies.iter().enumerate() {
+            grid.query(body.0, body.1, &mut found);
+            pairs += found.iter().filter(|&&other| other > index && overlaps(body, &bodies[other])).count();
+        }
+        pairs
+    });
+}
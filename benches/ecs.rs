@@ -0,0 +1,112 @@
+// Storage benchmark, run with `cargo bench --bench ecs`.
+//
+// Pulls the ECS modules in by path so the benchmark does not need SDL2. It
+// times a position+texture query over 100k entities against the same walk
+// over the HashMap storage the ECS used to have.
+// Unit tests in the included modules are compiled in but never run here
+#![allow(dead_code, unused_imports)]
+
+#[path = "../src/commands.rs"]
+mod commands;
+#[path = "../src/componentmanager.rs"]
+mod componentmanager;
+#[path = "../src/events.rs"]
+mod events;
+#[path = "../src/query.rs"]
+mod query;
+#[path = "../src/storage.rs"]
+mod storage;
+#[path = "../src/vec2.rs"]
+mod vec2;
+
+use componentmanager::{ComponentManager, Entity};
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::Instant;
+
+const ENTITIES: usize = 100_000;
+const RUNS: u32 = 100;
+
+struct Position {
+    x: f32,
+    y: f32
+}
+
+struct Texture {
+    size: (u32, u32),
+    color: u32
+}
+
+struct Movement {
+    x: f32,
+    y: f32
+}
+
+fn populate() -> ComponentManager {
+    let mut cmanager = ComponentManager::default();
+
+    for i in 0..ENTITIES {
+        let entity = cmanager.create_entity();
+        cmanager.insert(entity, Position { x: i as f32, y: i as f32 });
+        cmanager.insert(entity, Texture { size: (4, 4), color: i as u32 });
+
+        // Interleave a component most entities lack so storages differ in size
+        if i % 4 == 0 {
+            cmanager.insert(entity, Movement { x: 1.0, y: -1.0 });
+        }
+    }
+
+    cmanager
+}
+
+fn time<F: FnMut()>(name: &str, mut f: F) {
+    // Warm up once before timing
+    f();
+
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        f();
+    }
+    let per_run = start.elapsed() / RUNS;
+    let per_entity = per_run.as_nanos() as f64 / ENTITIES as f64;
+
+    println!("{:<40} {:>10.3?} per run {:>8.2} ns/entity", name, per_run, per_entity);
+}
+
+fn main() {
+    let cmanager = populate();
+
+    time("query (&Position, &Texture)", || {
+        let mut sum = 0.0;
+        cmanager.query::<(&Position, &Texture)>().for_each(|_, (position, texture)| {
+            sum += position.x + position.y + texture.size.0 as f32;
+        });
+        black_box(sum);
+    });
+
+    time("query (&mut Position, &Movement)", || {
+        cmanager.query::<(&mut Position, &Movement)>().for_each(|_, (position, movement)| {
+            position.x += movement.x;
+            position.y += movement.y;
+        });
+    });
+
+    // The old backend: one HashMap per component type keyed by entity
+    let mut positions: HashMap<Entity, Position> = HashMap::new();
+    let mut textures: HashMap<Entity, Texture> = HashMap::new();
+    cmanager.query::<(&Position, &Texture)>().for_each(|entity, (position, texture)| {
+        positions.insert(entity, Position { x: position.x, y: position.y });
+        textures.insert(entity, Texture { size: texture.size, color: texture.color });
+    });
+    let ids: Vec<Entity> = cmanager.allocated().to_vec();
+
+    time("HashMap lookup per allocated id", || {
+        let mut sum = 0.0;
+        for id in ids.iter() {
+            if let (Some(position), Some(texture)) = (positions.get(id), textures.get(id)) {
+                sum += position.x + position.y + texture.size.0 as f32;
+            }
+        }
+        black_box(sum);
+    });
+}
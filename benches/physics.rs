@@ -0,0 +1,139 @@
+// Physics step benchmark, run with `cargo bench --bench physics`.
+//
+// Runs the real SysPhysics over worlds of a few thousand colliders, most of
+// them dynamic, keeping the density the same as the body count grows. Time
+// per body should stay roughly flat if the broad phase does its job. The
+// systems module reaches most of the game, so unlike the other benchmarks
+// this one links SDL2, though it never initialises it.
+// Unit tests in the included modules are compiled in but never run here
+#![allow(dead_code, unused_imports)]
+
+#[path = "../src/ball.rs"]
+mod ball;
+#[path = "../src/broadphase.rs"]
+mod broadphase;
+#[path = "../src/collision.rs"]
+mod collision;
+#[path = "../src/commands.rs"]
+mod commands;
+#[path = "../src/componentmanager.rs"]
+mod componentmanager;
+#[path = "../src/components.rs"]
+mod components;
+#[path = "../src/controllers.rs"]
+mod controllers;
+#[path = "../src/events.rs"]
+mod events;
+#[path = "../src/fontmanager.rs"]
+mod fontmanager;
+#[path = "../src/gamestate.rs"]
+mod gamestate;
+#[path = "../src/input.rs"]
+mod input;
+#[path = "../src/paddle.rs"]
+mod paddle;
+#[path = "../src/query.rs"]
+mod query;
+#[path = "../src/resources.rs"]
+mod resources;
+#[path = "../src/storage.rs"]
+mod storage;
+#[path = "../src/systems.rs"]
+mod systems;
+#[path = "../src/util.rs"]
+mod util;
+#[path = "../src/vec2.rs"]
+mod vec2;
+
+use collision::Shape;
+use components::{CCollision2D, CMovement2D, CPosition2D, LAYER_BALL, LAYER_WALL};
+use events::Contact;
+use std::time::Instant;
+use systems::{SysPhysics, System};
+use util::CManagerRc;
+use vec2::Vec2;
+
+const TICKS: u32 = 50;
+const DELTA_TIME: f32 = 1.0 / 120.0;
+// Every tenth body is a static block, the rest are moving balls
+const STATIC_EVERY: usize = 10;
+// Field area per body, so bigger worlds are just as crowded
+const SPACING: f32 = 60.0;
+
+// Small LCG so the layout is the same every run without pulling in rand
+fn lcg(seed: u32) -> impl FnMut() -> f32 {
+    let mut state = seed;
+    move || {
+        state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        (state >> 8) as f32 / (1 << 24) as f32
+    }
+}
+
+fn populate(bodies: usize) -> CManagerRc {
+    let cmanager = util::create_component_manager();
+    let field = (bodies as f32).sqrt() * SPACING;
+    let mut next = lcg(0x2545_f491);
+
+    {
+        let mut cmanager = cmanager.borrow_mut();
+        cmanager.add_event::<Contact>();
+
+        for i in 0..bodies {
+            let id = cmanager.create_entity();
+            let pos = Vec2::new(next() * field, next() * field);
+            let is_static = i % STATIC_EVERY == 0;
+
+            let (shape, size, layer) = if is_static {
+                (Shape::Aabb, Vec2::new(40.0, 40.0), LAYER_WALL)
+            }
+            else {
+                (Shape::Circle, Vec2::new(10.0, 10.0), LAYER_BALL)
+            };
+
+            cmanager.insert(id, CCollision2D {
+                id,
+                name: "body".to_string(),
+                size,
+                shape,
+                layer,
+                mask: LAYER_BALL | LAYER_WALL,
+                is_static
+            });
+            cmanager.insert(id, CPosition2D::new(id, "body", pos));
+
+            if !is_static {
+                let angle = next() * std::f32::consts::TAU;
+                cmanager.insert(id, CMovement2D {
+                    id,
+                    name: "body".to_string(),
+                    speed: 300.0,
+                    x: angle.cos(),
+                    y: angle.sin()
+                });
+            }
+        }
+    }
+
+    cmanager
+}
+
+fn main() {
+    for bodies in [1_000, 2_000, 4_000, 8_000] {
+        let cmanager = populate(bodies);
+        let mut physics = SysPhysics::new(&cmanager);
+
+        // Warm up once before timing
+        physics.execute(DELTA_TIME);
+
+        let start = Instant::now();
+        for _ in 0..TICKS {
+            physics.execute(DELTA_TIME);
+            // Contacts are only sent here, drop them like a sync point would
+            cmanager.borrow_mut().clear_events();
+        }
+        let per_tick = start.elapsed() / TICKS;
+        let per_body = per_tick.as_nanos() as f64 / bodies as f64;
+
+        println!("{:<40} {:>10.3?} per tick {:>8.2} ns/body", format!("SysPhysics, {} bodies", bodies), per_tick, per_body);
+    }
+}
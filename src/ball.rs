@@ -1,61 +1,74 @@
 use sdl2::pixels::Color;
-use sdl2::rect::Point;
-use crate::components::{CMovement2D, CCollision2D};
+use crate::components::{CBall, CMovement2D, CCollision2D, LAYER_BALL, LAYER_PADDLE, LAYER_WALL};
 use crate::components::{CTexture, CPosition2D};
-use crate::components::Component;
+use crate::collision::Shape;
+use crate::componentmanager::{ComponentManager, Entity};
+use crate::resources::MatchConfig;
 use crate::util::{self, CManagerRc};
+use crate::vec2::Vec2;
 
-pub const START_BALL: (i32, i32) = (640, 360);
+pub const START_BALL: Vec2 = Vec2::new(640.0, 360.0);
+
+// Serves as many balls as the match is configured to start with
+pub fn create_all(cmanager: &CManagerRc) {
+    let ball_count = cmanager.borrow().resource::<MatchConfig>().map_or(1, |config| config.ball_count);
+
+    for _ in 0..ball_count {
+        create(cmanager);
+    }
+}
 
 pub fn create(cmanager: &CManagerRc) {
     let mut cmanager = cmanager.borrow_mut();
-    let id = cmanager.id_allocator.get_number();
+    let id = cmanager.create_entity();
+
+    let config = cmanager.resource::<MatchConfig>().map(|config| config.clone()).unwrap_or_default();
+    let direction = util::world_random_direction(&cmanager);
 
+    spawn(&mut cmanager, id, START_BALL, direction, config.ball_speed);
+}
+
+// Builds a ball on an already allocated entity, shared by serves and splits
+pub fn spawn(cmanager: &mut ComponentManager, id: Entity, pos: Vec2, direction: (f32, f32), speed: f32) {
     let name = "ball";
 
-    let collision_2d = CCollision2D {
+    let ball = CBall {
         id,
         name: name.to_string(),
-        size: (10, 10)
+        hits: 0
     };
 
-    let (x, y) = util::random_direction();
-    let movement_2d = CMovement2D {
-        id: id,
+    let collision_2d = CCollision2D {
+        id,
         name: name.to_string(),
-        speed: 540.0,
-        x,
-        y
+        size: Vec2::new(10.0, 10.0),
+        shape: Shape::Circle,
+        layer: LAYER_BALL,
+        mask: LAYER_PADDLE | LAYER_WALL,
+        is_static: false
     };
 
-    let position_2d = CPosition2D {
+    let movement_2d = CMovement2D {
         id,
         name: name.to_string(),
-        pos: Point::new(START_BALL.0, START_BALL.1)
+        speed,
+        x: direction.0,
+        y: direction.1
     };
 
+    let position_2d = CPosition2D::new(id, name, pos);
+
     let texture = CTexture {
         id,
         name: name.to_string(),
         size: (10, 10),
+        shape: Shape::Circle,
         color: Color::WHITE
     };
 
-    cmanager.ccollision_2d.insert(id, collision_2d);
-    cmanager.cmovement_2d.insert(id, movement_2d);
-    cmanager.cposition_2d.insert(id, position_2d);
-    cmanager.ctexture.insert(id, texture);
+    cmanager.insert(id, ball);
+    cmanager.insert(id, collision_2d);
+    cmanager.insert(id, movement_2d);
+    cmanager.insert(id, position_2d);
+    cmanager.insert(id, texture);
 }
-
-pub fn get_ball_id(cmanager: &CManagerRc) -> Option<usize> {
-    let mut ball_id = None;
-
-    for (_id, component) in &cmanager.borrow().cposition_2d {
-        let (pos_id, name) = component.get_info();
-        if name.eq_ignore_ascii_case("ball") {
-            ball_id = Some(pos_id);
-        }
-    }
-
-    ball_id
-}
\ No newline at end of file
@@ -0,0 +1,106 @@
+use crate::vec2::Vec2;
+use std::collections::hash_map::HashMap;
+
+/*
+    Uniform grid broad phase. Every box is filed under each cell it covers,
+    so a query only has to look at the boxes sharing a cell with the area
+    asked about instead of every box in the world. Boxes are identified by
+    the caller's own index, the grid never sees the boxes themselves.
+*/
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new()
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for cell in self.cells.values_mut() {
+            cell.clear();
+        }
+    }
+
+    pub fn insert(&mut self, index: usize, pos: Vec2, size: Vec2) {
+        for key in self.covered_cells(pos, size) {
+            self.cells.entry(key).or_default().push(index);
+        }
+    }
+
+    pub fn remove(&mut self, index: usize, pos: Vec2, size: Vec2) {
+        for key in self.covered_cells(pos, size) {
+            // Order within a cell doesn't matter, query sorts what it finds
+            let Some(cell) = self.cells.get_mut(&key) else {
+                continue;
+            };
+            if let Some(slot) = cell.iter().position(|&other| other == index) {
+                cell.swap_remove(slot);
+            }
+        }
+    }
+
+    // Indices of every box that may overlap the area, each listed once
+    pub fn query(&self, pos: Vec2, size: Vec2, found: &mut Vec<usize>) {
+        found.clear();
+
+        for key in self.covered_cells(pos, size) {
+            if let Some(cell) = self.cells.get(&key) {
+                found.extend_from_slice(cell);
+            }
+        }
+
+        found.sort_unstable();
+        found.dedup();
+    }
+
+    fn covered_cells(&self, pos: Vec2, size: Vec2) -> impl Iterator<Item = (i32, i32)> {
+        let half = size * 0.5;
+        let (min_x, min_y) = self.cell_of(pos - half);
+        let (max_x, max_y) = self.cell_of(pos + half);
+
+        (min_x..=max_x).flat_map(move |x| (min_y..=max_y).map(move |y| (x, y)))
+    }
+
+    fn cell_of(&self, point: Vec2) -> (i32, i32) {
+        ((point.x / self.cell_size).floor() as i32, (point.y / self.cell_size).floor() as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(grid: &SpatialGrid, pos: Vec2, size: Vec2) -> Vec<usize> {
+        let mut found = Vec::new();
+        grid.query(pos, size, &mut found);
+        found
+    }
+
+    #[test]
+    fn a_box_across_several_cells_is_found_once() {
+        let mut grid = SpatialGrid::new(10.0);
+        grid.insert(3, Vec2::new(10.0, 10.0), Vec2::new(15.0, 15.0));
+        grid.insert(1, Vec2::new(15.0, 5.0), Vec2::new(2.0, 2.0));
+
+        assert_eq!(query(&grid, Vec2::new(10.0, 10.0), Vec2::new(30.0, 30.0)), vec![1, 3]);
+        assert!(query(&grid, Vec2::new(100.0, 100.0), Vec2::new(5.0, 5.0)).is_empty());
+    }
+
+    #[test]
+    fn a_moved_box_is_only_found_where_it_went() {
+        let mut grid = SpatialGrid::new(10.0);
+        grid.insert(0, Vec2::new(5.0, 5.0), Vec2::new(4.0, 4.0));
+        grid.insert(1, Vec2::new(6.0, 6.0), Vec2::new(4.0, 4.0));
+
+        grid.remove(0, Vec2::new(5.0, 5.0), Vec2::new(4.0, 4.0));
+        grid.insert(0, Vec2::new(45.0, 45.0), Vec2::new(4.0, 4.0));
+
+        assert_eq!(query(&grid, Vec2::new(5.0, 5.0), Vec2::new(4.0, 4.0)), vec![1]);
+        assert_eq!(query(&grid, Vec2::new(45.0, 45.0), Vec2::new(4.0, 4.0)), vec![0]);
+    }
+}
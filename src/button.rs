@@ -1,5 +1,8 @@
-use crate::components::{CCollision2D, CTexture, CPosition2D, CButtonInfo, CText};
-use crate::util::CManagerRc;
+use crate::collision::Shape;
+use crate::componentmanager::Entity;
+use crate::components::{CCollision2D, CTexture, CPosition2D, CButtonInfo, CText, LAYER_NONE};
+use crate::util::{self, CManagerRc};
+use crate::vec2::Vec2;
 use sdl2::rect::Point;
 use sdl2::{rect::{Rect}, pixels::Color};
 
@@ -9,28 +12,29 @@ pub fn create(
         rect: Rect,
         color: Color,
         text: &str,
-        callback: Option<Box<dyn Fn() -> ()>>) {
+        callback: Option<Box<dyn Fn() -> ()>>) -> Entity {
     let mut cmanager = cmanager.borrow_mut();
-    let id = cmanager.id_allocator.get_number();
+    let id = cmanager.create_entity();
 
     let collision_2d = CCollision2D {
         id,
         name: name.to_string(),
-        size: rect.size()
+        size: Vec2::new(rect.width() as f32, rect.height() as f32),
+        shape: Shape::Aabb,
+        layer: LAYER_NONE,
+        mask: LAYER_NONE,
+        is_static: true
     };
 
     let texture = CTexture {
         id,
         name: name.to_string(),
         size: rect.size(),
+        shape: Shape::Aabb,
         color
     };
 
-    let position_2d = CPosition2D {
-        id,
-        name: name.to_string(),
-        pos: rect.center()
-    };
+    let position_2d = CPosition2D::new(id, name, util::to_vec2(rect.center()));
 
     let button_info = CButtonInfo {
         id,
@@ -48,11 +52,13 @@ pub fn create(
         color: Color::BLACK
     };
 
-    cmanager.ccollision_2d.insert(id, collision_2d);
-    cmanager.cposition_2d.insert(id, position_2d);
-    cmanager.ctexture.insert(id, texture);
-    cmanager.cbutton_info.insert(id, button_info);
-    cmanager.ctext.insert(id, text_component);
+    cmanager.insert(id, collision_2d);
+    cmanager.insert(id, position_2d);
+    cmanager.insert(id, texture);
+    cmanager.insert(id, button_info);
+    cmanager.insert(id, text_component);
+
+    id
 }
 
 /*
@@ -0,0 +1,274 @@
+use crate::vec2::Vec2;
+
+/*
+    Collider shapes, each fitted to the collider's size. A circle takes the
+    shorter side as its diameter.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    Aabb,
+    Circle
+}
+
+/*
+    Every shape is handled as a rounded box: an axis-aligned core grown by a
+    radius in all directions. A box has no radius and a circle has no core.
+    The Minkowski sum of two rounded boxes is again a rounded box, which lets
+    one sweep and one overlap test serve every pair of shapes.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoundedBox {
+    pub half: Vec2,
+    pub radius: f32
+}
+
+impl RoundedBox {
+    pub fn new(shape: Shape, size: Vec2) -> Self {
+        let half = size * 0.5;
+        let radius = half.x.min(half.y);
+
+        match shape {
+            Shape::Aabb => Self { half, radius: 0.0 },
+            Shape::Circle => Self { half: Vec2::ZERO, radius }
+        }
+    }
+
+    fn sum(self, other: Self) -> Self {
+        Self {
+            half: self.half + other.half,
+            radius: self.radius + other.radius
+        }
+    }
+
+    // Nearest point of the core to a point given relative to the centre
+    fn clamp_to_core(self, point: Vec2) -> Vec2 {
+        Vec2::new(point.x.clamp(-self.half.x, self.half.x), point.y.clamp(-self.half.y, self.half.y))
+    }
+}
+
+// Where in a step a moving shape first touches another, and which way it was pushed back
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit {
+    // Fraction of the step's motion covered before contact, from 0 to 1
+    pub time: f32,
+    // Unit normal of the surface that was hit, pointing back at the mover
+    pub normal: Vec2
+}
+
+/*
+    Swept test between two shapes. The static shape is grown by the moving
+    one so the problem becomes a ray from the mover's centre against a single
+    rounded box. The ray is clipped against the box grown by the full radius
+    first, and only if it enters beside one of the rounded corners is it
+    tested against that corner's circle. A mover starting inside the grown
+    box but in the empty pocket beside a rounded corner isn't touching yet,
+    so it goes straight to the corner test. Shapes that really overlap at the
+    start of the step report no hit, so a mover inside something can always
+    leave it.
+*/
+pub fn sweep(pos: Vec2, shape: RoundedBox, motion: Vec2, other_pos: Vec2, other_shape: RoundedBox) -> Option<Hit> {
+    let target = shape.sum(other_shape);
+    let reach = target.half + Vec2::new(target.radius, target.radius);
+
+    let (entry_x, exit_x) = slab(pos.x, motion.x, other_pos.x, reach.x)?;
+    let (entry_y, exit_y) = slab(pos.y, motion.y, other_pos.y, reach.y)?;
+
+    let entry = entry_x.max(entry_y);
+    let exit = exit_x.min(exit_y);
+
+    if entry >= exit || entry > 1.0 {
+        return None;
+    }
+
+    if entry < 0.0 {
+        let start = pos - other_pos;
+        let corner = target.clamp_to_core(start);
+        if target.radius == 0.0 || (start - corner).length() < target.radius {
+            return None;
+        }
+
+        return corner_hit(start - corner, motion, target.radius);
+    }
+
+    let contact = pos + motion * entry - other_pos;
+    let beside_face = contact.x.abs() <= target.half.x || contact.y.abs() <= target.half.y;
+
+    if target.radius == 0.0 || beside_face {
+        let normal = if entry_x > entry_y {
+            Vec2::new(-motion.x.signum(), 0.0)
+        }
+        else {
+            Vec2::new(0.0, -motion.y.signum())
+        };
+
+        return Some(Hit { time: entry, normal });
+    }
+
+    let corner = target.clamp_to_core(contact);
+    corner_hit(pos - other_pos - corner, motion, target.radius)
+}
+
+// Hit against a corner's circle, start given relative to the corner
+fn corner_hit(start: Vec2, motion: Vec2, radius: f32) -> Option<Hit> {
+    let time = ray_circle(start, motion, radius)?;
+    Some(Hit { time, normal: (start + motion * time).normalized() })
+}
+
+/*
+    Shortest push that separates two overlapping shapes, None if they don't
+    overlap. Touching counts as apart.
+*/
+pub fn penetration(pos: Vec2, shape: RoundedBox, other_pos: Vec2, other_shape: RoundedBox) -> Option<Vec2> {
+    let target = shape.sum(other_shape);
+    let delta = pos - other_pos;
+    let outside = delta - target.clamp_to_core(delta);
+    let distance = outside.length();
+
+    if distance > 0.0 {
+        if distance >= target.radius {
+            return None;
+        }
+
+        return Some(outside * ((target.radius - distance) / distance));
+    }
+
+    // Centre inside the core, back out along the shallowest axis
+    let depth = Vec2::new(
+        target.half.x + target.radius - delta.x.abs(),
+        target.half.y + target.radius - delta.y.abs());
+
+    if depth.x <= 0.0 || depth.y <= 0.0 {
+        return None;
+    }
+
+    if depth.x < depth.y {
+        Some(Vec2::new(depth.x * delta.x.signum(), 0.0))
+    }
+    else {
+        Some(Vec2::new(0.0, depth.y * delta.y.signum()))
+    }
+}
+
+// Times at which a point moving along one axis enters and leaves a slab
+fn slab(start: f32, motion: f32, center: f32, reach: f32) -> Option<(f32, f32)> {
+    let (min, max) = (center - reach, center + reach);
+
+    // Not moving on this axis, so it is either inside the slab all step or never
+    if motion == 0.0 {
+        return if start > min && start < max {
+            Some((f32::NEG_INFINITY, f32::INFINITY))
+        }
+        else {
+            None
+        };
+    }
+
+    let to_min = (min - start) / motion;
+    let to_max = (max - start) / motion;
+
+    Some((to_min.min(to_max), to_min.max(to_max)))
+}
+
+// When within the step a point heading inwards reaches a circle around the origin
+fn ray_circle(start: Vec2, motion: Vec2, radius: f32) -> Option<f32> {
+    let a = motion.dot(motion);
+    let b = start.dot(motion);
+    let c = start.dot(start) - radius * radius;
+
+    if a == 0.0 || c < 0.0 || b >= 0.0 {
+        return None;
+    }
+
+    let discriminant = b * b - a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let time = (-b - discriminant.sqrt()) / a;
+    (0.0..=1.0).contains(&time).then_some(time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: Vec2, expected: Vec2) {
+        assert!((actual - expected).length() < 0.001, "{:?} != {:?}", actual, expected);
+    }
+
+    fn circle(diameter: f32) -> RoundedBox {
+        RoundedBox::new(Shape::Circle, Vec2::new(diameter, diameter))
+    }
+
+    fn square(size: f32) -> RoundedBox {
+        RoundedBox::new(Shape::Aabb, Vec2::new(size, size))
+    }
+
+    #[test]
+    fn sweep_hits_a_face_head_on() {
+        let hit = sweep(Vec2::new(-20.0, 0.0), circle(10.0), Vec2::new(20.0, 0.0), Vec2::ZERO, square(10.0)).unwrap();
+
+        assert_eq!(hit.time, 0.5);
+        assert_eq!(hit.normal, Vec2::new(-1.0, 0.0));
+    }
+
+    #[test]
+    fn sweep_misses_what_it_passes_by_or_falls_short_of() {
+        let passing = sweep(Vec2::new(-20.0, 20.0), circle(10.0), Vec2::new(40.0, 0.0), Vec2::ZERO, square(10.0));
+        let short = sweep(Vec2::new(-20.0, 0.0), circle(10.0), Vec2::new(5.0, 0.0), Vec2::ZERO, square(10.0));
+
+        assert_eq!(passing, None);
+        assert_eq!(short, None);
+    }
+
+    #[test]
+    fn sweep_rounds_off_corners() {
+        let hit = sweep(Vec2::new(-20.0, -20.0), circle(10.0), Vec2::new(20.0, 20.0), Vec2::ZERO, square(10.0)).unwrap();
+
+        // The centre stops one radius short of the box's corner, along the diagonal
+        let travel = (15.0 * 2.0f32.sqrt() - 5.0) / (20.0 * 2.0f32.sqrt());
+        assert!((hit.time - travel).abs() < 0.001);
+        assert_near(hit.normal, Vec2::new(-1.0, -1.0).normalized());
+    }
+
+    #[test]
+    fn sweep_ignores_shapes_already_overlapping() {
+        let hit = sweep(Vec2::new(2.0, 0.0), circle(10.0), Vec2::new(-10.0, 0.0), Vec2::ZERO, square(10.0));
+
+        assert_eq!(hit, None);
+    }
+
+    #[test]
+    fn penetration_pushes_out_the_shortest_way() {
+        // Centre beside the box, pushed straight out to the radius
+        let beside = penetration(Vec2::new(8.0, 0.0), circle(10.0), Vec2::ZERO, square(10.0));
+        assert_near(beside.unwrap(), Vec2::new(2.0, 0.0));
+
+        // Centre inside the core, out along the shallower axis
+        let inside = penetration(Vec2::new(3.0, 1.0), square(10.0), Vec2::ZERO, square(10.0));
+        assert_eq!(inside, Some(Vec2::new(7.0, 0.0)));
+    }
+
+    #[test]
+    fn penetration_counts_touching_as_apart() {
+        assert_eq!(penetration(Vec2::new(10.0, 0.0), circle(10.0), Vec2::ZERO, square(10.0)), None);
+        assert_eq!(penetration(Vec2::new(10.0, 0.0), square(10.0), Vec2::ZERO, square(10.0)), None);
+    }
+
+    #[test]
+    fn sweep_hits_corner_from_inside_the_grown_box() {
+        let ball = RoundedBox::new(Shape::Circle, Vec2::new(10.0, 10.0));
+        let paddle = RoundedBox::new(Shape::Aabb, Vec2::new(10.0, 100.0));
+        let start = Vec2::new(9.0, 54.0);
+        let motion = Vec2::new(-6.0, -6.0);
+
+        assert_eq!(penetration(start, ball, Vec2::ZERO, paddle), None);
+
+        let hit = sweep(start, ball, motion, Vec2::ZERO, paddle).expect("corner should be hit");
+        assert!(hit.time > 0.0 && hit.time < 1.0);
+        assert!(hit.normal.x > 0.0 && hit.normal.y > 0.0);
+
+        let end = start + motion * hit.time;
+        assert_eq!(penetration(end, ball, Vec2::ZERO, paddle), None);
+    }
+}
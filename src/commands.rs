@@ -0,0 +1,95 @@
+use crate::componentmanager::{ComponentManager, Entity};
+
+type Command = Box<dyn FnOnce(&mut ComponentManager)>;
+
+/*
+    Structural changes queued by systems while they only hold a shared borrow
+    of the ComponentManager. The Schedule applies the queue at the end of each
+    stage, so nothing spawned or despawned here is visible until the next
+    stage runs.
+*/
+#[derive(Default)]
+pub struct Commands {
+    queue: Vec<Command>
+}
+
+impl Commands {
+    // Any change to the world, e.g. inserting or removing a component
+    pub fn add<F: FnOnce(&mut ComponentManager) + 'static>(&mut self, command: F) {
+        self.queue.push(Box::new(command));
+    }
+
+    // The entity is only allocated when the command is applied
+    pub fn spawn<F: FnOnce(&mut ComponentManager, Entity) + 'static>(&mut self, build: F) {
+        self.add(move |cmanager| {
+            let entity = cmanager.create_entity();
+            build(cmanager, entity);
+        });
+    }
+
+    pub fn despawn(&mut self, entity: Entity) {
+        self.add(move |cmanager| cmanager.free_entity(&entity));
+    }
+
+    pub(crate) fn take(&mut self) -> Vec<Command> {
+        std::mem::take(&mut self.queue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Marker(u32);
+
+    #[test]
+    fn nothing_changes_until_commands_are_applied() {
+        let mut cmanager = ComponentManager::default();
+        cmanager.commands().spawn(|cmanager, entity| cmanager.insert(entity, Marker(1)));
+        assert!(cmanager.allocated().is_empty());
+
+        cmanager.apply_commands();
+        let entity = cmanager.allocated()[0];
+        assert_eq!(cmanager.get::<Marker>(&entity).map(|marker| marker.0), Some(1));
+
+        cmanager.commands().despawn(entity);
+        assert!(cmanager.is_alive(&entity));
+        cmanager.apply_commands();
+        assert!(!cmanager.is_alive(&entity));
+        assert!(cmanager.get::<Marker>(&entity).is_none());
+    }
+
+    // Commands apply in the order they were queued, so later ones see what a spawn built
+    #[test]
+    fn commands_queued_after_a_spawn_see_the_spawned_entity() {
+        let mut cmanager = ComponentManager::default();
+        cmanager.commands().spawn(|cmanager, entity| cmanager.insert(entity, Marker(1)));
+        cmanager.commands().add(|cmanager| {
+            let spawned = cmanager.query::<&Marker>().entities();
+            cmanager.insert(spawned[0], 2u32);
+        });
+
+        cmanager.apply_commands();
+
+        let entity = cmanager.allocated()[0];
+        assert_eq!(cmanager.get::<u32>(&entity).map(|value| *value), Some(2));
+    }
+
+    #[test]
+    fn commands_queued_by_commands_are_applied_in_the_same_call() {
+        let mut cmanager = ComponentManager::default();
+        cmanager.commands().spawn(|cmanager, entity| {
+            cmanager.insert(entity, Marker(1));
+            cmanager.commands().spawn(move |cmanager, child| cmanager.insert(child, Marker(entity.index() as u32 + 10)));
+        });
+
+        cmanager.apply_commands();
+
+        let mut markers: Vec<u32> = cmanager.query::<&Marker>().entities().iter()
+            .map(|entity| cmanager.get::<Marker>(entity).unwrap().0)
+            .collect();
+        markers.sort();
+        assert_eq!(markers, vec![1, 10]);
+        assert!(cmanager.commands().take().is_empty());
+    }
+}
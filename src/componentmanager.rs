@@ -1,67 +1,350 @@
-use crate::components::{CMovement2D, CCollision2D, CText};
-use crate::components::{CPaddleInfo, CTexture, CPosition2D, CButtonInfo};
-use crate::components::Components;
+use crate::commands::Commands;
+use crate::events::{EventQueue, Events};
+use crate::query::{Fetch, Query};
+use crate::storage::Components;
+use std::any::{Any, TypeId};
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::hash_map::HashMap;
 
+/*
+    Type-erased view of a Components<T> so the manager can hold storages for
+    any component type and still strip a freed entity out of all of them.
+*/
+trait ComponentStorage {
+    fn remove_entity(&mut self, entity: &Entity);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> ComponentStorage for Components<T> {
+    fn remove_entity(&mut self, entity: &Entity) {
+        self.remove(entity);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/*
+    Storages sit behind their own RefCell so a system can hold, say, positions
+    mutably and collisions immutably at the same time. Borrowing the same
+    component type twice with one of them mutable will panic like any RefCell.
+*/
 #[derive(Default)]
 pub struct ComponentManager {
-    pub id_allocator: IDAllocator,
-
-    // Component lists
-    pub ccollision_2d: Components<CCollision2D>,
-    pub cmovement_2d: Components<CMovement2D>,
-    pub cpaddle_info: Components<CPaddleInfo>,
-    pub cposition_2d: Components<CPosition2D>,
-    pub ctexture: Components<CTexture>,
-    pub cbutton_info: Components<CButtonInfo>,
-    pub ctext: Components<CText>
+    id_allocator: IDAllocator,
+    storages: HashMap<TypeId, RefCell<Box<dyn ComponentStorage>>>,
+    resources: HashMap<TypeId, RefCell<Box<dyn Any>>>,
+    events: HashMap<TypeId, RefCell<Box<dyn EventQueue>>>,
+    commands: RefCell<Commands>
 }
 
 impl ComponentManager {
-    pub fn free_entity(&mut self, id: usize) {
-        self.ccollision_2d.remove(&id);
-        self.cmovement_2d.remove(&id);
-        self.cpaddle_info.remove(&id);
-        self.cposition_2d.remove(&id);
-        self.ctexture.remove(&id);
-        self.cbutton_info.remove(&id);
-        self.ctext.remove(&id);
+    pub fn is_alive(&self, entity: &Entity) -> bool {
+        self.id_allocator.is_alive(entity)
+    }
+
+    // Live entities, read only so an entity can't be freed without its components
+    pub fn allocated(&self) -> &[Entity] {
+        self.id_allocator.allocated()
+    }
+
+    pub fn create_entity(&mut self) -> Entity {
+        self.id_allocator.get_number()
+    }
+
+    pub fn register<T: 'static>(&mut self) {
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| RefCell::new(Box::new(Components::<T>::new())));
+    }
+
+    /*
+        Components for stale handles are dropped rather than stored, otherwise
+        they would be unreachable and never cleaned up.
+    */
+    pub fn insert<T: 'static>(&mut self, entity: Entity, component: T) {
+        if !self.is_alive(&entity) {
+            return;
+        }
+
+        self.register::<T>();
+        if let Some(mut components) = self.components_mut::<T>() {
+            components.insert(entity, component);
+        }
+    }
+
+    pub fn has<T: 'static>(&self, entity: &Entity) -> bool {
+        self.components::<T>().is_some_and(|components| components.contains_key(entity))
+    }
+
+    pub fn get<T: 'static>(&self, entity: &Entity) -> Option<Ref<'_, T>> {
+        Ref::filter_map(self.components::<T>()?, |components| components.get(entity)).ok()
+    }
+
+    /*
+        Iterate or look up entities by the components they have. See query.rs
+        for the terms that can be combined into Q.
+    */
+    pub fn query<Q: Fetch>(&self) -> Query<'_, Q> {
+        Query::new(self)
+    }
+
+    /*
+        Whole storage for one component type, or None if nothing of that type
+        has ever been inserted or registered.
+    */
+    pub fn components<T: 'static>(&self) -> Option<Ref<'_, Components<T>>> {
+        let storage = self.storages.get(&TypeId::of::<T>())?.borrow();
+        Ref::filter_map(storage, |storage| storage.as_any().downcast_ref::<Components<T>>()).ok()
+    }
+
+    pub fn components_mut<T: 'static>(&self) -> Option<RefMut<'_, Components<T>>> {
+        let storage = self.storages.get(&TypeId::of::<T>())?.borrow_mut();
+        RefMut::filter_map(storage, |storage| storage.as_any_mut().downcast_mut::<Components<T>>()).ok()
+    }
+
+    /*
+        Resources are singletons keyed by type, one of each at most. Inserting
+        a resource that already exists replaces it.
+    */
+    pub fn insert_resource<T: 'static>(&mut self, resource: T) {
+        self.resources.insert(TypeId::of::<T>(), RefCell::new(Box::new(resource)));
+    }
+
+    pub fn resource<T: 'static>(&self) -> Option<Ref<'_, T>> {
+        let resource = self.resources.get(&TypeId::of::<T>())?.borrow();
+        Ref::filter_map(resource, |resource| resource.downcast_ref::<T>()).ok()
+    }
+
+    pub fn resource_mut<T: 'static>(&self) -> Option<RefMut<'_, T>> {
+        let resource = self.resources.get(&TypeId::of::<T>())?.borrow_mut();
+        RefMut::filter_map(resource, |resource| resource.downcast_mut::<T>()).ok()
+    }
+
+    /*
+        Event types have to be added up front, sending an event nobody added
+        is treated as a bug rather than silently dropped.
+    */
+    pub fn add_event<T: 'static>(&mut self) {
+        self.events
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| RefCell::new(Box::new(Events::<T>::default())));
+    }
+
+    pub fn send_event<T: 'static>(&self, event: T) {
+        match self.events_mut::<T>() {
+            Some(mut events) => events.send(event),
+            None => panic!("Event {} was sent before being added.", std::any::type_name::<T>())
+        }
+    }
+
+    pub fn events<T: 'static>(&self) -> Option<Ref<'_, Events<T>>> {
+        let events = self.events.get(&TypeId::of::<T>())?.borrow();
+        Ref::filter_map(events, |events| events.as_any().downcast_ref::<Events<T>>()).ok()
+    }
+
+    pub fn events_mut<T: 'static>(&self) -> Option<RefMut<'_, Events<T>>> {
+        let events = self.events.get(&TypeId::of::<T>())?.borrow_mut();
+        RefMut::filter_map(events, |events| events.as_any_mut().downcast_mut::<Events<T>>()).ok()
+    }
 
-        self.id_allocator.free_number(id);
+    // Copies out the readable events so the caller is free to mutate the world
+    pub fn read_events<T: Clone + 'static>(&self) -> Vec<T> {
+        self.events::<T>().map_or_else(Vec::new, |events| events.iter().cloned().collect())
+    }
+
+    // Drops every pending and readable event, for when the world starts over
+    pub fn clear_events(&mut self) {
+        for events in self.events.values_mut() {
+            events.get_mut().clear();
+        }
+    }
+
+    /*
+        Opens the event channels for a reader, normally a schedule stage, so it
+        sees everything published since its previous run. Each reader needs
+        its own id.
+    */
+    pub fn begin_event_reads(&mut self, reader: usize) {
+        for events in self.events.values_mut() {
+            events.get_mut().begin_read(reader);
+        }
+    }
+
+    // Sync point for event channels, publishes what was sent since the last one
+    pub fn update_events(&mut self) {
+        for events in self.events.values_mut() {
+            events.get_mut().update();
+        }
+    }
+
+    /*
+        Queue for spawns, despawns and inserts requested while storages are
+        borrowed. Nothing happens until apply_commands is called.
+    */
+    pub fn commands(&self) -> RefMut<'_, Commands> {
+        self.commands.borrow_mut()
+    }
+
+    // Commands queued by other commands are applied in the same call
+    pub fn apply_commands(&mut self) {
+        loop {
+            let queue = self.commands.get_mut().take();
+            if queue.is_empty() {
+                break;
+            }
+
+            for command in queue {
+                command(self);
+            }
+        }
+    }
+
+    /*
+        Stale handles are ignored so that freeing an old handle can never
+        strip the components of whatever entity now occupies the slot.
+    */
+    pub fn free_entity(&mut self, entity: &Entity) {
+        if !self.is_alive(entity) {
+            return;
+        }
+
+        for storage in self.storages.values_mut() {
+            storage.get_mut().remove_entity(entity);
+        }
+
+        self.id_allocator.free_number(entity);
+    }
+}
+
+/*
+    Handle to an entity. The index names a slot in the allocator and the
+    generation records which occupant of that slot the handle refers to, so a
+    handle kept past free_entity no longer matches the slot's next occupant.
+*/
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct Entity {
+    index: usize,
+    generation: u32
+}
+
+impl Entity {
+    pub fn index(&self) -> usize {
+        self.index
     }
 }
 
 #[derive(Default)]
 pub struct IDAllocator {
-    next_id: usize,
+    generations: Vec<u32>,
     free_ids: Vec<usize>,
-    allocated_ids: Vec<usize>
+    allocated_ids: Vec<Entity>,
+    // Where each live slot sits in allocated_ids, so freeing needn't search for it
+    allocated_positions: Vec<usize>
 }
 
 impl IDAllocator {
-    pub fn get_number(&mut self) -> usize {
-        if let Some(id) = self.free_ids.pop() {
-            self.allocated_ids.push(id);
-            id
+    pub fn get_number(&mut self) -> Entity {
+        let entity = if let Some(index) = self.free_ids.pop() {
+            Entity { index, generation: self.generations[index] }
         }
         else {
-            let id = self.next_id;
-            self.next_id += 1;
-            self.allocated_ids.push(id);
-            id
-        }
+            let index = self.generations.len();
+            self.generations.push(0);
+            self.allocated_positions.push(0);
+            Entity { index, generation: 0 }
+        };
+
+        self.allocated_positions[entity.index] = self.allocated_ids.len();
+        self.allocated_ids.push(entity);
+        entity
+    }
+
+    pub fn is_alive(&self, entity: &Entity) -> bool {
+        self.generations.get(entity.index) == Some(&entity.generation)
+    }
+
+    // Live entities, in no particular order since freeing swaps the last one into the gap
+    pub fn allocated(&self) -> &[Entity] {
+        &self.allocated_ids
     }
 
     /*
-        Prefer calling wrapper function of the same name in utility.rs
+        Bumping the generation on free invalidates every outstanding handle to
+        the slot. Returns false if the handle was already stale.
     */
-    pub fn get_allocated_ids(&self) -> Vec<usize> {
-        self.allocated_ids[..].to_vec()
+    pub fn free_number(&mut self, entity: &Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+
+        self.generations[entity.index] = entity.generation.wrapping_add(1);
+        self.free_ids.push(entity.index);
+
+        let position = self.allocated_positions[entity.index];
+        self.allocated_ids.swap_remove(position);
+        if let Some(moved) = self.allocated_ids.get(position) {
+            self.allocated_positions[moved.index] = position;
+        }
+        true
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stale_handle_is_rejected_after_its_slot_is_reused() {
+        let mut cmanager = ComponentManager::default();
+        let old = cmanager.create_entity();
+        cmanager.insert(old, 1u32);
+        cmanager.free_entity(&old);
+
+        let new = cmanager.create_entity();
+        assert_eq!(new.index(), old.index());
+        assert_ne!(new.generation, old.generation);
 
-    pub fn free_number(&mut self, id: usize) {
-        if !self.free_ids.contains(&id) {
-            self.free_ids.push(id);
-            self.allocated_ids.retain(|&x| x != id )
+        assert!(!cmanager.is_alive(&old));
+        assert!(cmanager.get::<u32>(&new).is_none());
+
+        // Writes through the old handle go nowhere
+        cmanager.insert(old, 2u32);
+        assert!(cmanager.get::<u32>(&old).is_none());
+        assert!(cmanager.get::<u32>(&new).is_none());
+
+        // Nor can it free the slot's new occupant
+        cmanager.free_entity(&old);
+        assert!(cmanager.is_alive(&new));
+        assert_eq!(cmanager.allocated(), &[new]);
+    }
+
+    #[test]
+    fn freeing_keeps_the_allocated_list_in_step() {
+        let mut allocator = IDAllocator::default();
+        let entities: Vec<Entity> = (0..5).map(|_| allocator.get_number()).collect();
+
+        assert!(allocator.free_number(&entities[1]));
+        assert!(allocator.free_number(&entities[4]));
+        assert!(!allocator.free_number(&entities[1]));
+
+        let mut allocated = allocator.allocated().to_vec();
+        allocated.sort_by_key(Entity::index);
+        assert_eq!(allocated, vec![entities[0], entities[2], entities[3]]);
+
+        // The entity swapped into the freed spot can still be freed itself
+        for entity in [entities[3], entities[0], entities[2]] {
+            assert!(allocator.free_number(&entity));
         }
+        assert!(allocator.allocated().is_empty());
+
+        let reused = allocator.get_number();
+        assert_eq!(allocator.allocated(), &[reused]);
     }
-}
\ No newline at end of file
+}
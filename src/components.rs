@@ -1,23 +1,31 @@
 use sdl2::pixels::Color;
 use sdl2::rect::Point;
-use std::time::Instant;
+use crate::collision::{RoundedBox, Shape};
+use crate::componentmanager::Entity;
+use crate::events::Side;
+use crate::input::Action;
 use crate::paddle::Direction;
-use std::collections::hash_map::HashMap;
+use crate::vec2::Vec2;
 
-pub type Components<T> = HashMap<usize, T>;
-
-pub trait Component {
-    fn get_info(&self) -> (usize, &str); 
-}
+// Collision layers, a body collides with anything whose layer is in its mask
+pub const LAYER_NONE: u32 = 0;
+pub const LAYER_BALL: u32 = 1;
+pub const LAYER_PADDLE: u32 = 1 << 1;
+pub const LAYER_WALL: u32 = 1 << 2;
 
 pub struct CCollision2D {
-    pub id: usize,
+    pub id: Entity,
     pub name: String,
-    pub size: (u32, u32)
+    pub size: Vec2,
+    pub shape: Shape,
+    pub layer: u32,
+    pub mask: u32,
+    // Static bodies are never moved by physics, though other systems may move them
+    pub is_static: bool
 }
 
 pub struct CMovement2D {
-    pub id: usize,
+    pub id: Entity,
     pub name: String,
     pub speed: f32,
     pub x: f32,
@@ -25,29 +33,45 @@ pub struct CMovement2D {
 }
 
 pub struct CPaddleInfo {
-    pub id: usize,
+    pub id: Entity,
     pub name: String,
     pub direction: Direction,
-    pub ai_delay_timer: Instant,
-    pub ai_delay: u128,
+    // Simulation time since the AI last picked a direction, and how long it waits between picks
+    pub ai_delay_elapsed: f32,
+    pub ai_delay: f32,
     pub is_ai: bool
 }
 
+// Actions that steer a human paddle, paddles without one are left to the AI
+pub struct CInputBinding {
+    pub id: Entity,
+    pub name: String,
+    // Picks the controller that plays this paddle
+    pub side: Side,
+    pub up: Action,
+    pub down: Action,
+    // Chases the mouse pointer when no keys are held
+    pub follows_mouse: bool
+}
+
 pub struct CPosition2D {
-    pub id: usize,
+    pub id: Entity,
     pub name: String,
-    pub pos: Point
+    pub pos: Vec2,
+    // Where the entity was at the start of the latest tick
+    pub prev: Vec2
 }
 
 pub struct CTexture {
-    pub id: usize,
+    pub id: Entity,
     pub name: String,
     pub size: (u32, u32),
+    pub shape: Shape,
     pub color: Color
 }
 
 pub struct CText {
-    pub id: usize,
+    pub id: Entity,
     pub name: String,
     pub text: String,
     pub size: u32,
@@ -55,27 +79,61 @@ pub struct CText {
     pub color: Color
 }
 
+// Marks an entity as a ball for scoring, AI targeting and splitting
+pub struct CBall {
+    pub id: Entity,
+    pub name: String,
+    // Paddle hits since this ball was served or split off
+    pub hits: u32
+}
+
 pub struct CButtonInfo {
-    pub id: usize,
+    pub id: Entity,
     pub name: String,
     pub text: String,
     pub callback: Option<Box<dyn Fn() -> ()>>
 }
 
-impl CPaddleInfo {
-    pub fn is_delay_done(&self) -> bool {
-        self.ai_delay_timer.elapsed().as_millis() > self.ai_delay
+impl CPosition2D {
+    pub fn new(id: Entity, name: &str, pos: Vec2) -> Self {
+        Self {
+            id,
+            name: name.to_string(),
+            pos,
+            prev: pos
+        }
+    }
+
+    // Moves without interpolating from the old position, for resets and spawns
+    pub fn teleport(&mut self, pos: Vec2) {
+        self.pos = pos;
+        self.prev = pos;
+    }
+
+    pub fn interpolated(&self, alpha: f32) -> Vec2 {
+        self.prev.lerp(self.pos, alpha)
     }
 }
 
-impl Component for CCollision2D {
-    fn get_info(&self) -> (usize, &str) {
-        (self.id, &self.name)
+impl CCollision2D {
+    pub fn half_size(&self) -> Vec2 {
+        self.size * 0.5
+    }
+
+    pub fn rounded_box(&self) -> RoundedBox {
+        RoundedBox::new(self.shape, self.size)
     }
 }
 
-impl Component for CPosition2D {
-    fn get_info(&self) -> (usize, &str) {
-        (self.id, &self.name)
+impl CPaddleInfo {
+    // Advances the delay by a tick, true once it has run out and starts over
+    pub fn tick_ai_delay(&mut self, delta_time: f32) -> bool {
+        self.ai_delay_elapsed += delta_time;
+        if self.ai_delay_elapsed <= self.ai_delay {
+            return false;
+        }
+
+        self.ai_delay_elapsed = 0.0;
+        true
     }
-}
\ No newline at end of file
+}
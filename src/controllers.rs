@@ -0,0 +1,206 @@
+use crate::events::Side;
+use sdl2::controller::{Axis, Button, GameController};
+use sdl2::event::Event;
+use sdl2::GameControllerSubsystem;
+
+// Stick travel ignored around the centre so a worn stick doesn't drift the paddle
+const DEAD_ZONE: f32 = 0.15;
+
+struct Pad {
+    controller: GameController,
+    side: Option<Side>
+}
+
+/*
+    Open game controllers and the paddle each one plays. SDL reports the
+    controllers already plugged in at startup as added devices too, so they
+    are all opened from the event stream. A new controller takes the first
+    side nobody has, and can be moved to another side from the lobby.
+*/
+pub struct Controllers {
+    subsystem: GameControllerSubsystem,
+    pads: Vec<Pad>
+}
+
+impl Controllers {
+    pub fn new(subsystem: GameControllerSubsystem) -> Self {
+        Self {
+            subsystem,
+            pads: Vec::new()
+        }
+    }
+
+    pub fn update(&mut self, events: &[Event]) {
+        for event in events {
+            match event {
+                Event::ControllerDeviceAdded { which, .. } => self.open(*which),
+                Event::ControllerDeviceRemoved { which, .. } => self.close(*which),
+                _ => {}
+            }
+        }
+    }
+
+    // Added events carry the device index, every later event the instance id
+    fn open(&mut self, index: u32) {
+        let controller = match self.subsystem.open(index) {
+            Ok(controller) => controller,
+            Err(error) => {
+                println!("Could not open controller {}: {}", index, error);
+                return;
+            }
+        };
+
+        let id = controller.instance_id();
+        if self.pads.iter().any(|pad| pad.controller.instance_id() == id) {
+            return;
+        }
+
+        let side = [Side::Left, Side::Right].into_iter().find(|side| self.pad_for(*side).is_none());
+        println!("Connected {} to {:?}", controller.name(), side);
+
+        self.pads.push(Pad { controller, side });
+    }
+
+    fn close(&mut self, id: u32) {
+        self.pads.retain(|pad| pad.controller.instance_id() != id);
+    }
+
+    // Hands a side to a controller, whichever one had it before sits out
+    pub fn assign(&mut self, id: u32, side: Side) {
+        for pad in self.pads.iter_mut() {
+            if pad.controller.instance_id() == id {
+                pad.side = Some(side);
+            }
+            else if pad.side == Some(side) {
+                pad.side = None;
+            }
+        }
+    }
+
+    fn pad_for(&self, side: Side) -> Option<&Pad> {
+        self.pads.iter().find(|pad| pad.side == Some(side))
+    }
+
+    /*
+        How hard the controller playing a side pushes its paddle, from -1 for
+        full speed up to 1 for full speed down. The D-pad counts as the stick
+        pushed all the way. None if nobody plays that side with a controller
+        or it is left at rest.
+    */
+    pub fn steering(&self, side: Side) -> Option<f32> {
+        let controller = &self.pad_for(side)?.controller;
+
+        if controller.button(Button::DPadUp) {
+            return Some(-1.0);
+        }
+        if controller.button(Button::DPadDown) {
+            return Some(1.0);
+        }
+
+        let value = controller.axis(Axis::LeftY) as f32 / i16::MAX as f32;
+        if value.abs() <= DEAD_ZONE {
+            return None;
+        }
+
+        // Rescaled so the paddle starts from a crawl at the edge of the dead zone
+        let amount = ((value.abs() - DEAD_ZONE) / (1.0 - DEAD_ZONE)).min(1.0);
+        Some(amount * value.signum())
+    }
+
+    // Name and side of every connected controller, for the lobby
+    pub fn assignments(&self) -> Vec<(String, Option<Side>)> {
+        self.pads.iter().map(|pad| (pad.controller.name(), pad.side)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sdl2::sys;
+
+    // A virtual joystick with SDL's default controller mapping, axes and buttons in SDL's own order
+    fn attach() -> i32 {
+        unsafe {
+            let index = sys::SDL_JoystickAttachVirtual(
+                sys::SDL_JoystickType::SDL_JOYSTICK_TYPE_GAMECONTROLLER,
+                sys::SDL_GameControllerAxis::SDL_CONTROLLER_AXIS_MAX as i32,
+                sys::SDL_GameControllerButton::SDL_CONTROLLER_BUTTON_MAX as i32,
+                0
+            );
+            assert!(index >= 0, "{}", sdl2::get_error());
+            index
+        }
+    }
+
+    fn joystick(id: u32) -> *mut sys::SDL_Joystick {
+        unsafe { sys::SDL_JoystickFromInstanceID(id as i32) }
+    }
+
+    fn set_axis(subsystem: &GameControllerSubsystem, id: u32, axis: Axis, value: i16) {
+        unsafe { sys::SDL_JoystickSetVirtualAxis(joystick(id), axis as i32, value) };
+        subsystem.update();
+    }
+
+    fn set_button(subsystem: &GameControllerSubsystem, id: u32, button: Button, pressed: bool) {
+        unsafe { sys::SDL_JoystickSetVirtualButton(joystick(id), button as i32, pressed as u8) };
+        subsystem.update();
+    }
+
+    fn sides(controllers: &Controllers) -> Vec<Option<Side>> {
+        controllers.assignments().into_iter().map(|(_, side)| side).collect()
+    }
+
+    // SDL only allows one context at a time, so every check shares this one test
+    #[test]
+    fn virtual_controllers() {
+        let sdl = sdl2::init().unwrap();
+        let subsystem = sdl.game_controller().unwrap();
+        let mut event_pump = sdl.event_pump().unwrap();
+        let mut controllers = Controllers::new(subsystem.clone());
+        let mut pump = |controllers: &mut Controllers| {
+            let events: Vec<Event> = event_pump.poll_iter().collect();
+            controllers.update(&events);
+        };
+
+        // Hotplug: each new controller takes the first free side
+        let first_index = attach();
+        let second_index = attach();
+        pump(&mut controllers);
+        assert_eq!(sides(&controllers), vec![Some(Side::Left), Some(Side::Right)]);
+
+        let first = unsafe { sys::SDL_JoystickGetDeviceInstanceID(first_index) } as u32;
+        let second = unsafe { sys::SDL_JoystickGetDeviceInstanceID(second_index) } as u32;
+
+        // Taking a side benches whoever had it
+        controllers.assign(first, Side::Right);
+        assert_eq!(sides(&controllers), vec![Some(Side::Right), None]);
+        controllers.assign(second, Side::Left);
+        assert_eq!(sides(&controllers), vec![Some(Side::Right), Some(Side::Left)]);
+
+        // Inside the dead zone the stick is at rest
+        set_axis(&subsystem, first, Axis::LeftY, (i16::MAX as f32 * 0.1) as i16);
+        assert_eq!(controllers.steering(Side::Right), None);
+
+        // Past it the travel is rescaled to start from zero
+        set_axis(&subsystem, first, Axis::LeftY, i16::MAX / 2);
+        let expected = (0.5 - DEAD_ZONE) / (1.0 - DEAD_ZONE);
+        let steering = controllers.steering(Side::Right).unwrap();
+        assert!((steering - expected).abs() < 0.001, "{} != {}", steering, expected);
+
+        // All the way up, one step further than i16::MAX, still caps at full speed
+        set_axis(&subsystem, first, Axis::LeftY, i16::MIN);
+        assert_eq!(controllers.steering(Side::Right), Some(-1.0));
+
+        // The D-pad is the stick pushed all the way
+        set_axis(&subsystem, first, Axis::LeftY, 0);
+        set_button(&subsystem, first, Button::DPadDown, true);
+        assert_eq!(controllers.steering(Side::Right), Some(1.0));
+        assert_eq!(controllers.steering(Side::Left), None);
+
+        // Unplugging closes the controller and frees its side
+        unsafe { sys::SDL_JoystickDetachVirtual(first_index) };
+        pump(&mut controllers);
+        assert_eq!(sides(&controllers), vec![Some(Side::Left)]);
+        assert_eq!(controllers.steering(Side::Right), None);
+    }
+}
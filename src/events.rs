@@ -0,0 +1,227 @@
+use crate::componentmanager::Entity;
+use crate::vec2::Vec2;
+use std::any::Any;
+use std::collections::VecDeque;
+use std::ops::Range;
+
+/*
+    Channel for one event type. Events sent during a stage are published at
+    that stage's sync point, and every stage keeps a cursor into the stream:
+    each run of a stage reads whatever was published since its previous run
+    started. Every system therefore reads each event exactly once whichever
+    stage it sits in, whether that stage is stepped several times per frame,
+    once, or not at all on some frames. Events are dropped once every stage
+    has read past them, so a stage that stops running holds on to what it
+    hasn't read until it runs again. Outside of a stage everything still
+    held is readable.
+*/
+pub struct Events<T> {
+    pending: Vec<T>,
+    published: VecDeque<T>,
+    // Sequence number of the oldest published event still held
+    first: usize,
+    // Per reading stage, where its next run starts reading, None until it has run
+    cursors: Vec<Option<usize>>,
+    // Sequence numbers the running stage may read
+    window: Range<usize>
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self {
+            pending: Vec::new(),
+            published: VecDeque::new(),
+            first: 0,
+            cursors: Vec::new(),
+            window: 0..0
+        }
+    }
+}
+
+impl<T> Events<T> {
+    pub fn send(&mut self, event: T) {
+        self.pending.push(event);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        let start = self.window.start.max(self.first);
+        self.published.iter().skip(start - self.first).take(self.window.end.saturating_sub(start))
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.first = self.end();
+        self.published.clear();
+        self.window = self.first..self.first;
+    }
+
+    // Sequence number the next published event will get
+    fn end(&self) -> usize {
+        self.first + self.published.len()
+    }
+
+    // Called as a stage starts, opens its window and moves its cursor past it
+    fn begin_read(&mut self, reader: usize) {
+        if self.cursors.len() <= reader {
+            self.cursors.resize(reader + 1, None);
+        }
+
+        let start = self.cursors[reader].unwrap_or(self.first).max(self.first);
+        self.window = start..self.end();
+        self.cursors[reader] = Some(self.window.end);
+    }
+
+    // Called at every sync point, publishes what was sent and drops what every stage has read
+    fn update(&mut self) {
+        self.published.extend(self.pending.drain(..));
+
+        let oldest = self.cursors.iter().flatten().min().copied().unwrap_or(self.first);
+        while self.first < oldest && self.published.pop_front().is_some() {
+            self.first += 1;
+        }
+
+        self.window = self.first..self.end();
+    }
+}
+
+/*
+    Type-erased view of an Events<T> so the ComponentManager can drive every
+    channel at stage boundaries without knowing the event types.
+*/
+pub(crate) trait EventQueue {
+    fn begin_read(&mut self, reader: usize);
+    fn update(&mut self);
+    fn clear(&mut self);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> EventQueue for Events<T> {
+    fn begin_read(&mut self, reader: usize) {
+        Events::begin_read(self, reader);
+    }
+
+    fn update(&mut self) {
+        Events::update(self);
+    }
+
+    fn clear(&mut self) {
+        Events::clear(self);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Side {
+    Left,
+    Right
+}
+
+// The side that was awarded the point
+#[derive(Debug, Clone, Copy)]
+pub struct GoalScored {
+    pub side: Side,
+    pub ball: Entity
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PaddleHit {
+    pub ball: Entity
+}
+
+// A moving body ran into another and was bounced off it
+#[derive(Debug, Clone, Copy)]
+pub struct Contact {
+    pub entity: Entity,
+    pub other: Entity,
+    // Face normal of other, pointing back at entity
+    pub normal: Vec2,
+    // Where entity sat relative to other's centre at the moment of contact
+    pub offset: Vec2
+}
+
+// A button was clicked or confirmed from the keyboard or a controller
+#[derive(Debug, Clone, Copy)]
+pub struct ButtonClicked {
+    pub entity: Entity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: usize = 0;
+    const SIMULATION: usize = 1;
+    const SCORING: usize = 2;
+    const RENDER: usize = 3;
+
+    // Runs one stage: reads its window, sends, then hits the sync point
+    fn run(events: &mut Events<u32>, stage: usize, send: &[u32]) -> Vec<u32> {
+        events.begin_read(stage);
+        let read = events.iter().copied().collect();
+        for &event in send {
+            events.send(event);
+        }
+        events.update();
+        read
+    }
+
+    #[test]
+    fn per_frame_reader_sees_every_tick_once() {
+        let mut events = Events::default();
+
+        run(&mut events, INPUT, &[]);
+        run(&mut events, SIMULATION, &[1]);
+        run(&mut events, SCORING, &[]);
+        run(&mut events, SIMULATION, &[2]);
+        run(&mut events, SCORING, &[]);
+        assert_eq!(run(&mut events, RENDER, &[]), vec![1, 2]);
+
+        // A frame without ticks must not hand the same events out again
+        assert_eq!(run(&mut events, INPUT, &[]), vec![1, 2]);
+        assert_eq!(run(&mut events, RENDER, &[]), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn per_tick_reader_sees_each_event_once() {
+        let mut events = Events::default();
+
+        assert!(run(&mut events, SIMULATION, &[1]).is_empty());
+        assert_eq!(run(&mut events, SCORING, &[]), vec![1]);
+        assert_eq!(run(&mut events, SIMULATION, &[]), vec![1]);
+        assert!(run(&mut events, SCORING, &[]).is_empty());
+        assert!(run(&mut events, SIMULATION, &[]).is_empty());
+    }
+
+    #[test]
+    fn events_are_dropped_once_every_stage_has_read_them() {
+        let mut events = Events::default();
+
+        run(&mut events, INPUT, &[1]);
+        run(&mut events, RENDER, &[]);
+        assert_eq!(events.published.len(), 1);
+
+        run(&mut events, INPUT, &[]);
+        assert!(events.published.is_empty());
+    }
+
+    #[test]
+    fn clear_drops_pending_and_published() {
+        let mut events = Events::default();
+
+        run(&mut events, INPUT, &[1]);
+        events.send(2);
+        events.clear();
+        events.update();
+
+        assert!(run(&mut events, INPUT, &[]).is_empty());
+        assert_eq!(events.iter().count(), 0);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::button;
+use crate::events::{ButtonClicked, Side};
+use crate::gamestate::{GameState, GameStates, Transition};
+use crate::input::Action;
+use crate::resources::MatchResult;
+use crate::schedule::{Schedule, Stage};
+use crate::systems::{SysRenderTexture, SysButtonCallback, SysButtonInput, SysRenderText};
+use crate::util::{CanvasRc, EventRc, FontRc, TransitionRc, self, CManagerRc, SummaryRc};
+use sdl2::pixels::Color;
+use sdl2::rect::{Rect, Point};
+use sdl2::render::BlendMode;
+use sdl2::event::Event;
+use std::rc::Rc;
+
+/*
+    Overlay pushed by the match once the rules settle it. The finished match
+    stays frozen underneath while this shows the result, and either restarts
+    it or drops back to the main menu.
+*/
+pub struct GameOverState<'ttf> {
+    cmanager: CManagerRc,
+    schedule: Schedule<'ttf>,
+    canvas: CanvasRc,
+    event_runner: EventRc,
+    font_manager: FontRc<'ttf>,
+    transitions: TransitionRc,
+    summary: SummaryRc
+}
+
+impl<'ttf> GameOverState<'ttf> {
+    pub fn new(
+            canvas: &CanvasRc,
+            event_runner: &EventRc,
+            font_manager: &FontRc<'ttf>,
+            transitions: &TransitionRc,
+            summary: &SummaryRc) -> Self {
+        let cmanager: CManagerRc = util::create_component_manager();
+        cmanager.borrow_mut().add_event::<ButtonClicked>();
+
+        let mut schedule = Schedule::new(&cmanager);
+        schedule.add_system(Stage::Input, "button_input", SysButtonInput::new(&cmanager, event_runner));
+        schedule.add_system(Stage::Simulation, "button_callback", SysButtonCallback::new(&cmanager));
+        schedule.add_system(Stage::Render, "render_texture", SysRenderTexture::new(&cmanager, canvas));
+        schedule.add_system(Stage::Render, "render_text", SysRenderText::new(&cmanager, canvas, font_manager))
+            .after("render_texture");
+
+        let gos = Self {
+            cmanager,
+            schedule,
+            canvas: Rc::clone(canvas),
+            event_runner: Rc::clone(event_runner),
+            font_manager: Rc::clone(font_manager),
+            transitions: Rc::clone(transitions),
+            summary: Rc::clone(summary)
+        };
+
+        gos.init();
+
+        gos
+    }
+
+    fn init(&self) {
+        let rematch_rect = Rect::from_center(Point::new(640, 432), 192, 96);
+        let transitions = Rc::clone(&self.transitions);
+        let rematch_callback = Box::new(move || {
+            util::request_transition(&transitions, Transition::Pop);
+            util::request_transition(&transitions, Transition::Restart(GameStates::Pong));
+        });
+        button::create(&self.cmanager, "brematch", rematch_rect, Color::WHITE, "Rematch", Some(rematch_callback));
+
+        let menu_rect = Rect::from_center(Point::new(640, 544), 192, 96);
+        let transitions = Rc::clone(&self.transitions);
+        let menu_callback = Box::new(move || {
+            util::request_transition(&transitions, Transition::Pop);
+            util::request_transition(&transitions, Transition::Replace(GameStates::MainMenu));
+        });
+        button::create(&self.cmanager, "bmenu", menu_rect, Color::WHITE, "Menu", Some(menu_callback));
+    }
+
+    fn process_input(&mut self) {
+        let event_runner = self.event_runner.borrow();
+
+        for event in &event_runner.event_list {
+            if let Event::Quit {..} = event {
+                util::request_transition(&self.transitions, Transition::Quit);
+            }
+        }
+
+        if event_runner.actions.pressed(Action::Back) {
+            util::request_transition(&self.transitions, Transition::Pop);
+            util::request_transition(&self.transitions, Transition::Replace(GameStates::MainMenu));
+        }
+        else if event_runner.actions.pressed(Action::Confirm) {
+            util::request_transition(&self.transitions, Transition::Pop);
+            util::request_transition(&self.transitions, Transition::Restart(GameStates::Pong));
+        }
+    }
+
+    fn render_backdrop(&mut self) {
+        let (width, height) = self.canvas.borrow().window().size();
+        let mut canvas = self.canvas.borrow_mut();
+
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(Color::RGBA(0, 0, 0, 160));
+        canvas.fill_rect(Rect::new(0, 0, width, height)).unwrap();
+        canvas.set_blend_mode(BlendMode::None);
+    }
+
+    fn render_summary(&mut self) {
+        let Some(summary) = *self.summary.borrow() else {
+            return;
+        };
+
+        let headline = match summary.result {
+            MatchResult::Winner(Side::Left) => "Player 1 Wins",
+            MatchResult::Winner(Side::Right) => "Player 2 Wins",
+            MatchResult::Draw => "Draw"
+        };
+        let final_score = format!("{} - {}", summary.score.p1, summary.score.p2);
+
+        let mut font_manager = self.font_manager.borrow_mut();
+        font_manager.render_text(headline, Point::new(640, 200), "arial", 144, &self.canvas, Color::WHITE);
+        font_manager.render_text(&final_score, Point::new(640, 310), "arial", 72, &self.canvas, Color::WHITE);
+    }
+}
+
+impl<'ttf> GameState for GameOverState<'ttf> {
+    fn update(&mut self, delta_time: f32) {
+        self.process_input();
+        self.schedule.run_stages(&Stage::UPDATE, delta_time);
+    }
+
+    fn render(&mut self, delta_time: f32) {
+        self.render_backdrop();
+        self.schedule.run_stage(Stage::Render, delta_time);
+
+        self.render_summary();
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
@@ -1,10 +1,299 @@
+use crate::util::TransitionRc;
+use std::collections::hash_map::HashMap;
+use std::rc::Rc;
+
 pub trait GameState {
-    fn execute(&mut self, delta_time: f32);
+    // Input and simulation, only run for the state on top of the stack
+    fn update(&mut self, delta_time: f32);
+
+    // Drawing, also run for paused states showing through an overlay
+    fn render(&mut self, delta_time: f32);
+
+    // Overlays let the state below them keep rendering while paused
+    fn is_overlay(&self) -> bool {
+        false
+    }
+
+    fn on_enter(&mut self) {}
+    fn on_exit(&mut self) {}
+    fn on_pause(&mut self) {}
+    fn on_resume(&mut self) {}
+
+    // Throw away progress and start over, for states where that means something
+    fn restart(&mut self) {}
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum GameStates {
     Pong,
     MainMenu,
-    PauseMenu
-}
\ No newline at end of file
+    PauseMenu,
+    GameOver
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Transition {
+    Push(GameStates),
+    Pop,
+    Replace(GameStates),
+    Restart(GameStates),
+    Quit
+}
+
+/*
+    Stack of registered states. States and callbacks never change the stack
+    directly, they queue a Transition on the shared TransitionRc and the main
+    loop applies the queue between frames.
+
+    Push pauses the current top and enters the new state, Pop exits the top
+    and resumes the one beneath it, and Replace swaps the top without the
+    state below noticing. Restart leaves the stack alone and asks a state to
+    start over, wherever it sits.
+
+    Two inputs firing on the same frame, Confirm and a click on a button, or
+    a pause on the frame the match is won, queue transitions that were never
+    meant to meet. So only the last push queued in a frame is applied,
+    pushing or replacing in a state already on the stack does nothing, and
+    Pop never takes the bottom state off. Only Quit empties the stack.
+*/
+pub struct StateMachine<'a> {
+    states: HashMap<GameStates, Box<dyn GameState + 'a>>,
+    stack: Vec<GameStates>,
+    transitions: TransitionRc
+}
+
+impl<'a> StateMachine<'a> {
+    pub fn new(transitions: &TransitionRc) -> Self {
+        Self {
+            states: HashMap::new(),
+            stack: Vec::new(),
+            transitions: Rc::clone(transitions)
+        }
+    }
+
+    pub fn register(&mut self, key: GameStates, state: Box<dyn GameState + 'a>) {
+        self.states.insert(key, state);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    pub fn top(&self) -> Option<GameStates> {
+        self.stack.last().copied()
+    }
+
+    fn state(&mut self, key: GameStates) -> &mut Box<dyn GameState + 'a> {
+        match self.states.get_mut(&key) {
+            Some(state) => state,
+            None => panic!("Game state {:?} is not registered.", key)
+        }
+    }
+
+    fn push(&mut self, key: GameStates) {
+        if self.stack.contains(&key) {
+            return;
+        }
+
+        if let Some(top) = self.top() {
+            self.state(top).on_pause();
+        }
+
+        self.stack.push(key);
+        self.state(key).on_enter();
+    }
+
+    fn pop(&mut self) {
+        if self.stack.len() <= 1 {
+            return;
+        }
+
+        if let Some(top) = self.stack.pop() {
+            self.state(top).on_exit();
+        }
+
+        if let Some(top) = self.top() {
+            self.state(top).on_resume();
+        }
+    }
+
+    fn replace(&mut self, key: GameStates) {
+        if self.stack.contains(&key) {
+            return;
+        }
+
+        if let Some(top) = self.stack.pop() {
+            self.state(top).on_exit();
+        }
+
+        self.stack.push(key);
+        self.state(key).on_enter();
+    }
+
+    fn quit(&mut self) {
+        while let Some(top) = self.stack.pop() {
+            self.state(top).on_exit();
+        }
+    }
+
+    pub fn apply_transitions(&mut self) {
+        let mut transitions: Vec<Transition> = self.transitions.borrow_mut().drain(..).collect();
+
+        let is_push = |transition: &Transition| matches!(transition, Transition::Push(_));
+        if let Some(last_push) = transitions.iter().rposition(is_push) {
+            let mut index = 0;
+            transitions.retain(|transition| {
+                index += 1;
+                index - 1 == last_push || !is_push(transition)
+            });
+        }
+
+        for transition in transitions {
+            match transition {
+                Transition::Push(key) => self.push(key),
+                Transition::Pop => self.pop(),
+                Transition::Replace(key) => self.replace(key),
+                Transition::Restart(key) => self.state(key).restart(),
+                Transition::Quit => self.quit()
+            }
+        }
+    }
+
+    /*
+        Updates the top state, then renders from the highest non-overlay state
+        upwards so every overlay draws on top of what it paused.
+    */
+    pub fn execute(&mut self, delta_time: f32) {
+        let Some(top) = self.top() else {
+            return;
+        };
+
+        self.state(top).update(delta_time);
+
+        let mut first_visible = self.stack.len() - 1;
+        while first_visible > 0 && self.state(self.stack[first_visible]).is_overlay() {
+            first_visible -= 1;
+        }
+
+        for index in first_visible..self.stack.len() {
+            let key = self.stack[index];
+            self.state(key).render(delta_time);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util;
+    use std::cell::RefCell;
+
+    struct Blank;
+
+    impl GameState for Blank {
+        fn update(&mut self, _delta_time: f32) {}
+        fn render(&mut self, _delta_time: f32) {}
+    }
+
+    fn machine(transitions: &TransitionRc) -> StateMachine<'static> {
+        let mut machine = StateMachine::new(transitions);
+        for key in [GameStates::MainMenu, GameStates::Pong, GameStates::PauseMenu, GameStates::GameOver] {
+            machine.register(key, Box::new(Blank));
+        }
+
+        machine
+    }
+
+    #[test]
+    fn same_push_twice_in_a_frame_enters_once() {
+        let transitions: TransitionRc = Rc::new(RefCell::new(Vec::new()));
+        let mut machine = machine(&transitions);
+
+        util::request_transition(&transitions, Transition::Push(GameStates::MainMenu));
+        machine.apply_transitions();
+        util::request_transition(&transitions, Transition::Push(GameStates::Pong));
+        util::request_transition(&transitions, Transition::Push(GameStates::Pong));
+        machine.apply_transitions();
+
+        assert_eq!(machine.stack, vec![GameStates::MainMenu, GameStates::Pong]);
+    }
+
+    #[test]
+    fn pushing_a_state_already_on_the_stack_is_ignored() {
+        let transitions: TransitionRc = Rc::new(RefCell::new(Vec::new()));
+        let mut machine = machine(&transitions);
+
+        util::request_transition(&transitions, Transition::Push(GameStates::MainMenu));
+        machine.apply_transitions();
+        util::request_transition(&transitions, Transition::Push(GameStates::MainMenu));
+        machine.apply_transitions();
+
+        assert_eq!(machine.stack, vec![GameStates::MainMenu]);
+    }
+
+    // Pausing on the frame the match is won goes straight to the game over screen
+    #[test]
+    fn last_push_of_a_frame_wins() {
+        let transitions: TransitionRc = Rc::new(RefCell::new(Vec::new()));
+        let mut machine = machine(&transitions);
+
+        util::request_transition(&transitions, Transition::Push(GameStates::Pong));
+        machine.apply_transitions();
+        util::request_transition(&transitions, Transition::Push(GameStates::PauseMenu));
+        util::request_transition(&transitions, Transition::Push(GameStates::GameOver));
+        machine.apply_transitions();
+
+        assert_eq!(machine.stack, vec![GameStates::Pong, GameStates::GameOver]);
+
+        util::request_transition(&transitions, Transition::Pop);
+        util::request_transition(&transitions, Transition::Replace(GameStates::MainMenu));
+        machine.apply_transitions();
+
+        assert_eq!(machine.stack, vec![GameStates::MainMenu]);
+    }
+
+    #[test]
+    fn replace_swaps_the_top_unless_the_state_is_already_on_the_stack() {
+        let transitions: TransitionRc = Rc::new(RefCell::new(Vec::new()));
+        let mut machine = machine(&transitions);
+
+        util::request_transition(&transitions, Transition::Push(GameStates::MainMenu));
+        machine.apply_transitions();
+        util::request_transition(&transitions, Transition::Replace(GameStates::Pong));
+        machine.apply_transitions();
+        assert_eq!(machine.stack, vec![GameStates::Pong]);
+
+        util::request_transition(&transitions, Transition::Push(GameStates::PauseMenu));
+        machine.apply_transitions();
+        util::request_transition(&transitions, Transition::Replace(GameStates::Pong));
+        machine.apply_transitions();
+        assert_eq!(machine.stack, vec![GameStates::Pong, GameStates::PauseMenu]);
+    }
+
+    // Escape and a click on the pause menu's Menu button in the same frame
+    #[test]
+    fn doubled_input_never_pops_the_bottom_state() {
+        let transitions: TransitionRc = Rc::new(RefCell::new(Vec::new()));
+        let mut machine = machine(&transitions);
+
+        util::request_transition(&transitions, Transition::Push(GameStates::Pong));
+        machine.apply_transitions();
+        util::request_transition(&transitions, Transition::Push(GameStates::PauseMenu));
+        machine.apply_transitions();
+
+        util::request_transition(&transitions, Transition::Pop);
+        util::request_transition(&transitions, Transition::Pop);
+        util::request_transition(&transitions, Transition::Replace(GameStates::MainMenu));
+        machine.apply_transitions();
+        assert_eq!(machine.stack, vec![GameStates::MainMenu]);
+
+        util::request_transition(&transitions, Transition::Pop);
+        util::request_transition(&transitions, Transition::Pop);
+        machine.apply_transitions();
+        assert!(!machine.is_empty());
+
+        util::request_transition(&transitions, Transition::Quit);
+        machine.apply_transitions();
+        assert!(machine.is_empty());
+    }
+}
@@ -0,0 +1,365 @@
+use crate::events::Side;
+use sdl2::controller::Button;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::Keycode;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+/*
+    Bindings used when the bindings file is missing or can't be read. They
+    live in code rather than being parsed so the fallback itself can't fail,
+    and match the bindings.cfg shipped with the game.
+*/
+const DEFAULT_BINDINGS: &[(Action, Input)] = &[
+    (Action::MoveUp(Side::Left), Input::Key(Keycode::W)),
+    (Action::MoveDown(Side::Left), Input::Key(Keycode::S)),
+    (Action::MoveUp(Side::Right), Input::Key(Keycode::Up)),
+    (Action::MoveDown(Side::Right), Input::Key(Keycode::Down)),
+    (Action::Pause, Input::Key(Keycode::Escape)),
+    (Action::Pause, Input::Key(Keycode::P)),
+    (Action::Pause, Input::Pad(Button::Start)),
+    (Action::Confirm, Input::Key(Keycode::Return)),
+    (Action::Confirm, Input::Key(Keycode::Space)),
+    (Action::Confirm, Input::Pad(Button::A)),
+    (Action::Back, Input::Key(Keycode::Escape)),
+    (Action::Back, Input::Pad(Button::B)),
+    (Action::MenuUp, Input::Key(Keycode::Up)),
+    (Action::MenuUp, Input::Pad(Button::DPadUp)),
+    (Action::MenuDown, Input::Key(Keycode::Down)),
+    (Action::MenuDown, Input::Pad(Button::DPadDown)),
+    (Action::MenuLeft, Input::Pad(Button::DPadLeft)),
+    (Action::MenuRight, Input::Pad(Button::DPadRight))
+];
+
+/*
+    What the player wants to do, as opposed to which key they pressed.
+    States and systems only ever ask about actions, so remapping a key is
+    a change to the bindings file rather than to the code.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveUp(Side),
+    MoveDown(Side),
+    Pause,
+    Confirm,
+    Back,
+    MenuUp,
+    MenuDown,
+    MenuLeft,
+    MenuRight
+}
+
+impl Action {
+    pub const ALL: [Action; 11] = [
+        Action::MoveUp(Side::Left),
+        Action::MoveDown(Side::Left),
+        Action::MoveUp(Side::Right),
+        Action::MoveDown(Side::Right),
+        Action::Pause,
+        Action::Confirm,
+        Action::Back,
+        Action::MenuUp,
+        Action::MenuDown,
+        Action::MenuLeft,
+        Action::MenuRight
+    ];
+
+    // Name the action goes by in the bindings file
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveUp(Side::Left) => "move_up_left",
+            Action::MoveDown(Side::Left) => "move_down_left",
+            Action::MoveUp(Side::Right) => "move_up_right",
+            Action::MoveDown(Side::Right) => "move_down_right",
+            Action::Pause => "pause",
+            Action::Confirm => "confirm",
+            Action::Back => "back",
+            Action::MenuUp => "menu_up",
+            Action::MenuDown => "menu_down",
+            Action::MenuLeft => "menu_left",
+            Action::MenuRight => "menu_right"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+// Something an action can be bound to, a pad button counts on any controller
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Input {
+    Key(Keycode),
+    Pad(Button)
+}
+
+impl Input {
+    // Controller buttons are written with a pad: prefix, e.g. pad:a or pad:start
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.strip_prefix("pad:") {
+            Some(button) => Button::from_string(button).map(Input::Pad),
+            None => Keycode::from_name(name).map(Input::Key)
+        }
+    }
+}
+
+/*
+    Keys and controller buttons currently down, kept across frames from their
+    down and up events. They are in the order they were pressed so the most
+    recent one can win when two held inputs disagree. Key repeats are not
+    presses, and everything is let go when the window loses focus since the
+    up events would go to another window. The pointer's last known position
+    is kept alongside.
+*/
+#[derive(Default)]
+pub struct HeldInput {
+    inputs: Vec<Input>,
+    mouse_position: Option<(i32, i32)>
+}
+
+impl HeldInput {
+    pub fn update(&mut self, events: &[Event]) {
+        for event in events {
+            match event {
+                Event::KeyDown { keycode: Some(key), repeat: false, .. } => self.press(Input::Key(*key)),
+                Event::KeyUp { keycode: Some(key), .. } => self.release(Input::Key(*key)),
+                Event::ControllerButtonDown { button, .. } => self.press(Input::Pad(*button)),
+                Event::ControllerButtonUp { button, .. } => self.release(Input::Pad(*button)),
+                Event::MouseMotion { x, y, .. } => {
+                    self.mouse_position = Some((*x, *y));
+                },
+                Event::Window { win_event: WindowEvent::FocusLost, .. } => {
+                    self.inputs.clear();
+                },
+                _ => {}
+            }
+        }
+    }
+
+    fn press(&mut self, input: Input) {
+        self.inputs.retain(|held| *held != input);
+        self.inputs.push(input);
+    }
+
+    fn release(&mut self, input: Input) {
+        self.inputs.retain(|held| *held != input);
+    }
+
+    // Oldest press first
+    pub fn inputs(&self) -> &[Input] {
+        &self.inputs
+    }
+
+    // None until the mouse has moved over the window
+    pub fn mouse_position(&self) -> Option<(i32, i32)> {
+        self.mouse_position
+    }
+}
+
+/*
+    Turns the frame's input events and held inputs into action state. An
+    action is held while any of its inputs is down and pressed on a frame one
+    of them goes down. One input may trigger several actions, Escape both
+    pauses and backs out of menus.
+*/
+pub struct ActionMap {
+    bindings: HashMap<Input, Vec<Action>>,
+    // Held actions, ordered by the latest press of any of their inputs
+    held: Vec<Action>,
+    pressed: HashSet<Action>,
+    // Instance ids of the controllers that pressed each action this frame
+    pressed_on_pads: HashMap<Action, Vec<u32>>
+}
+
+impl Default for ActionMap {
+    fn default() -> Self {
+        let mut map = Self::empty();
+        for &(action, input) in DEFAULT_BINDINGS {
+            map.bind(input, action);
+        }
+
+        map
+    }
+}
+
+impl ActionMap {
+    fn empty() -> Self {
+        Self {
+            bindings: HashMap::new(),
+            held: Vec::new(),
+            pressed: HashSet::new(),
+            pressed_on_pads: HashMap::new()
+        }
+    }
+
+    // Reads a bindings file, keeping the defaults if it is missing or broken
+    pub fn load(path: &str) -> Self {
+        let Ok(text) = fs::read_to_string(path) else {
+            println!("No bindings at {}, using the defaults.", path);
+            return Self::default();
+        };
+
+        Self::parse(&text).unwrap_or_else(|error| {
+            println!("Could not load bindings from {}: {}", path, error);
+            Self::default()
+        })
+    }
+
+    /*
+        Parses `action = Key, Key` lines. Blank lines and lines starting with
+        # are skipped, and an action listed twice gets the inputs of both lines.
+    */
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut map = Self::empty();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let line_number = index + 1;
+            let (name, keys) = line.split_once('=')
+                .ok_or_else(|| format!("line {}: expected `action = keys`", line_number))?;
+            let action = Action::from_name(name.trim())
+                .ok_or_else(|| format!("line {}: unknown action `{}`", line_number, name.trim()))?;
+
+            for key in keys.split(',').map(str::trim).filter(|key| !key.is_empty()) {
+                let input = Input::from_name(key)
+                    .ok_or_else(|| format!("line {}: unknown input `{}`", line_number, key))?;
+                map.bind(input, action);
+            }
+        }
+
+        Ok(map)
+    }
+
+    pub fn bind(&mut self, input: Input, action: Action) {
+        let actions = self.bindings.entry(input).or_default();
+        if !actions.contains(&action) {
+            actions.push(action);
+        }
+    }
+
+    // Called once per frame after the held input has seen the frame's events
+    pub fn update(&mut self, events: &[Event], held_input: &HeldInput) {
+        self.pressed.clear();
+        self.pressed_on_pads.clear();
+
+        for event in events {
+            let (input, pad) = match event {
+                Event::KeyDown { keycode: Some(key), repeat: false, .. } => (Input::Key(*key), None),
+                Event::ControllerButtonDown { which, button, .. } => (Input::Pad(*button), Some(*which)),
+                _ => continue
+            };
+
+            for action in self.actions_for(input) {
+                self.pressed.insert(action);
+                if let Some(pad) = pad {
+                    self.pressed_on_pads.entry(action).or_default().push(pad);
+                }
+            }
+        }
+
+        self.held.clear();
+        for input in held_input.inputs() {
+            for action in self.actions_for(*input) {
+                self.held.retain(|held| *held != action);
+                self.held.push(action);
+            }
+        }
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    // Controllers that pressed the action this frame, for menus that care which pad it was
+    pub fn pressed_on_pads(&self, action: Action) -> &[u32] {
+        self.pressed_on_pads.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    // Whichever of the actions was pressed most recently and is still held
+    pub fn latest_held(&self, actions: &[Action]) -> Option<Action> {
+        self.held.iter().rev().find(|held| actions.contains(held)).copied()
+    }
+
+    fn actions_for(&self, input: Input) -> Vec<Action> {
+        self.bindings.get(&input).cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sdl2::keyboard::Mod;
+
+    fn key_down(key: Keycode) -> Event {
+        Event::KeyDown { timestamp: 0, window_id: 0, keycode: Some(key), scancode: None, keymod: Mod::NOMOD, repeat: false }
+    }
+
+    fn key_up(key: Keycode) -> Event {
+        Event::KeyUp { timestamp: 0, window_id: 0, keycode: Some(key), scancode: None, keymod: Mod::NOMOD, repeat: false }
+    }
+
+    // Feeds one frame's events through the held input and the map, as EventRunner does
+    fn frame(map: &mut ActionMap, held: &mut HeldInput, events: &[Event]) {
+        held.update(events);
+        map.update(events, held);
+    }
+
+    #[test]
+    fn parse_reads_keys_and_pad_buttons() {
+        let text = "
+            # comment
+            move_up_left = W, Up
+            pause = Escape, pad:start
+
+            pause = P
+        ";
+        let map = ActionMap::parse(text).unwrap();
+
+        assert_eq!(map.actions_for(Input::Key(Keycode::W)), vec![Action::MoveUp(Side::Left)]);
+        assert_eq!(map.actions_for(Input::Key(Keycode::Up)), vec![Action::MoveUp(Side::Left)]);
+        assert_eq!(map.actions_for(Input::Pad(Button::Start)), vec![Action::Pause]);
+        assert_eq!(map.actions_for(Input::Key(Keycode::P)), vec![Action::Pause]);
+        assert!(map.actions_for(Input::Key(Keycode::S)).is_empty());
+    }
+
+    #[test]
+    fn parse_reports_the_broken_line() {
+        let unknown_action = ActionMap::parse("pause = P\njump = Space").err().unwrap();
+        let unknown_key = ActionMap::parse("pause = NotAKey").err().unwrap();
+        let missing_equals = ActionMap::parse("\npause P").err().unwrap();
+
+        assert!(unknown_action.starts_with("line 2:"), "{}", unknown_action);
+        assert!(unknown_key.contains("NotAKey"), "{}", unknown_key);
+        assert!(missing_equals.starts_with("line 2:"), "{}", missing_equals);
+    }
+
+    #[test]
+    fn latest_held_prefers_the_last_press_and_falls_back_on_release() {
+        let mut map = ActionMap::default();
+        let mut held = HeldInput::default();
+        let (up, down) = (Action::MoveUp(Side::Left), Action::MoveDown(Side::Left));
+
+        frame(&mut map, &mut held, &[key_down(Keycode::W)]);
+        assert!(map.pressed(up));
+        assert_eq!(map.latest_held(&[up, down]), Some(up));
+
+        frame(&mut map, &mut held, &[key_down(Keycode::S)]);
+        assert!(!map.pressed(up));
+        assert_eq!(map.latest_held(&[up, down]), Some(down));
+        assert_eq!(map.latest_held(&[up]), Some(up));
+
+        frame(&mut map, &mut held, &[key_up(Keycode::S)]);
+        assert_eq!(map.latest_held(&[up, down]), Some(up));
+
+        // Pressing again moves an action that was already held to the front
+        frame(&mut map, &mut held, &[key_down(Keycode::S), key_up(Keycode::W), key_down(Keycode::W)]);
+        assert_eq!(map.latest_held(&[up, down]), Some(up));
+
+        frame(&mut map, &mut held, &[key_up(Keycode::W), key_up(Keycode::S)]);
+        assert_eq!(map.latest_held(&[up, down]), None);
+    }
+}
@@ -1,29 +1,45 @@
 mod ball;
+mod broadphase;
 mod button;
+mod collision;
+mod commands;
 mod componentmanager;
+mod controllers;
 mod components;
+mod events;
 mod fontmanager;
+mod gameoverstate;
 mod gamestate;
+mod input;
 mod mainmenustate;
 mod paddle;
+mod pausemenustate;
 mod pongstate;
+mod query;
+mod resources;
+mod schedule;
+mod storage;
 mod systems;
+mod timestep;
 mod util;
+mod wall;
+mod vec2;
 
 use fontmanager::FontManager;
-use gamestate::{GameState, GameStates};
+use controllers::Controllers;
+use gameoverstate::GameOverState;
+use gamestate::{GameStates, StateMachine, Transition};
+use input::{ActionMap, HeldInput};
 use mainmenustate::MainMenuState;
+use pausemenustate::PauseMenuState;
 use pongstate::PongState;
+use resources::MatchSetup;
 use sdl2::pixels::Color;
 use sdl2::render::Canvas;
 use sdl2::Sdl;
 use sdl2::video::Window;
-use std::collections::hash_map::HashMap;
 use std::time::{Instant, Duration};
-use util::{EventRunner, PlayRc, EventRc};
-
-static mut GAME_STATE: GameStates = GameStates::MainMenu;
-static mut PLAY: bool = false;
+use util::{EventRunner, EventRc, MatchSetupRc, SummaryRc, TransitionRc};
 
 pub fn main() {
     // Base SDL2 bind classes
@@ -33,55 +49,53 @@ pub fn main() {
     let font_manager = util::rcf(FontManager::new(&ttf_context));
 
     // Game State data
-    let mut game_states: HashMap<GameStates, Box<dyn GameState>> = HashMap::new();
+    let transitions: TransitionRc = util::rcf(Vec::new());
+    let mut state_machine = StateMachine::new(&transitions);
+    let summary: SummaryRc = util::rcf(None);
+    let setup: MatchSetupRc = util::rcf(MatchSetup::default());
 
     let event_runner: EventRc = util::rcf(EventRunner {
         event_pump: sdl_context.event_pump().unwrap(),
-        event_list: Vec::new()
+        event_list: Vec::new(),
+        held: HeldInput::default(),
+        actions: ActionMap::load("bindings.cfg"),
+        controllers: Controllers::new(sdl_context.game_controller().unwrap())
     });
     let mut now = Instant::now();
 
-    let pong_state = PongState::new(&canvas, &event_runner, &font_manager);
-    let menu_state = MainMenuState::new(&canvas, &event_runner, &font_manager);
+    let pong_state = PongState::new(&canvas, &event_runner, &font_manager, &transitions, &summary, &setup);
+    let menu_state = MainMenuState::new(&canvas, &event_runner, &font_manager, &transitions, &setup);
+    let pause_state = PauseMenuState::new(&canvas, &event_runner, &font_manager, &transitions);
+    let game_over_state = GameOverState::new(&canvas, &event_runner, &font_manager, &transitions, &summary);
 
-    game_states.insert(GameStates::Pong, Box::new(pong_state));
-    game_states.insert(GameStates::MainMenu, Box::new(menu_state));
+    state_machine.register(GameStates::Pong, Box::new(pong_state));
+    state_machine.register(GameStates::MainMenu, Box::new(menu_state));
+    state_machine.register(GameStates::PauseMenu, Box::new(pause_state));
+    state_machine.register(GameStates::GameOver, Box::new(game_over_state));
 
-    util::set_play(true);
+    util::request_transition(&transitions, Transition::Push(GameStates::MainMenu));
 
     'running: loop {
         // Sleep for 1/60th of a second, in nanoseconds
         ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 60));
 
-        let delta_time = now.elapsed().as_millis() as f32 / 1_000.0;
+        // Real frame time, the states turn it into fixed simulation ticks
+        let delta_time = now.elapsed().as_secs_f32();
+        now = Instant::now();
 
+        event_runner.borrow_mut().refresh();
 
-        let mut state_key = GameStates::MainMenu;
-        unsafe {
-            state_key = GAME_STATE;
+        state_machine.apply_transitions();
+        if state_machine.is_empty() {
+            break 'running;
         }
 
-        if let Some(state) = game_states.get_mut(&state_key) {
-            event_runner.borrow_mut().refresh();
-
-            unsafe {
-                if !(PLAY) {
-                    break 'running;
-                }
-            }
-
-            canvas.borrow_mut().set_draw_color(Color::RGB(0, 0, 0));
-            canvas.borrow_mut().clear();
+        canvas.borrow_mut().set_draw_color(Color::RGB(0, 0, 0));
+        canvas.borrow_mut().clear();
 
-            state.execute(delta_time);
+        state_machine.execute(delta_time);
 
-            canvas.borrow_mut().present();
-        }
-        else {
-            panic!("No game state is specified.");
-        }
-
-        now = Instant::now();
+        canvas.borrow_mut().present();
     }
 }
 
@@ -1,42 +1,51 @@
-use crate::{button, GAME_STATE};
-use crate::gamestate::{GameState, GameStates};
-use crate::systems::{SysRenderTexture, System, SysButtonInput, SysRenderText};
-use crate::util::{CanvasRc, EventRc, FontRc, PlayRc, GameStateRc, self, CManagerRc};
+use crate::button;
+use crate::gamestate::{GameState, GameStates, Transition};
+use crate::input::Action;
+use crate::events::{ButtonClicked, Side};
+use crate::resources::PlayerMode;
+use crate::schedule::{Schedule, Stage};
+use crate::systems::{SysRenderTexture, SysButtonCallback, SysButtonInput, SysRenderText};
+use crate::util::{CanvasRc, EventRc, FontRc, MatchSetupRc, TransitionRc, self, CManagerRc};
 use sdl2::pixels::Color;
 use sdl2::rect::{Rect, Point};
 use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
 use std::rc::Rc;
 
 pub struct MainMenuState<'ttf> {
     cmanager: CManagerRc,
-    render_system: SysRenderTexture,
-    button_input_system: SysButtonInput,
-    text_render_system: SysRenderText<'ttf>,
+    schedule: Schedule<'ttf>,
     canvas: CanvasRc,
     event_runner: EventRc,
-    font_manager: FontRc<'ttf>
+    font_manager: FontRc<'ttf>,
+    transitions: TransitionRc,
+    setup: MatchSetupRc
 }
 
 impl<'ttf> MainMenuState<'ttf> {
     pub fn new(
             canvas: &CanvasRc,
             event_runner: &EventRc,
-            font_manager: &FontRc<'ttf>) -> Self {
+            font_manager: &FontRc<'ttf>,
+            transitions: &TransitionRc,
+            setup: &MatchSetupRc) -> Self {
         let cmanager: CManagerRc = util::create_component_manager();
-        
-        let srender = SysRenderTexture::new(&cmanager, canvas);
-        let sbutton_input = SysButtonInput::new(&cmanager, event_runner, canvas);
-        let srendertext = SysRenderText::new(&cmanager, canvas, font_manager);
+        cmanager.borrow_mut().add_event::<ButtonClicked>();
+
+        let mut schedule = Schedule::new(&cmanager);
+        schedule.add_system(Stage::Input, "button_input", SysButtonInput::new(&cmanager, event_runner));
+        schedule.add_system(Stage::Simulation, "button_callback", SysButtonCallback::new(&cmanager));
+        schedule.add_system(Stage::Render, "render_texture", SysRenderTexture::new(&cmanager, canvas));
+        schedule.add_system(Stage::Render, "render_text", SysRenderText::new(&cmanager, canvas, font_manager))
+            .after("render_texture");
 
         let mms = Self {
             cmanager,
-            render_system: srender,
-            button_input_system: sbutton_input,
-            canvas: Rc::clone(&canvas),
-            event_runner: Rc::clone(&event_runner),
-            font_manager: Rc::clone(&font_manager),
-            text_render_system: srendertext
+            schedule,
+            canvas: Rc::clone(canvas),
+            event_runner: Rc::clone(event_runner),
+            font_manager: Rc::clone(font_manager),
+            transitions: Rc::clone(transitions),
+            setup: Rc::clone(setup)
         };
 
         mms.init();
@@ -45,34 +54,129 @@ impl<'ttf> MainMenuState<'ttf> {
     }
 
     fn init(&self) {
-        let main_menu_rect = Rect::from_center(Point::new(640, 360), 192, 96);
-        let main_menu_callback = Box::new(|| {
-            println!("Main Menu!");
-            util::change_gamestate(GameStates::Pong);
-        });
-        button::create(&self.cmanager, "bvplayer", main_menu_rect, Color::WHITE, "Play", Some(main_menu_callback));
+        let one_player_rect = Rect::from_center(Point::new(640, 304), 192, 96);
+        let one_player_callback = self.play_callback(PlayerMode::OnePlayer);
+        button::create(&self.cmanager, "bvplayer", one_player_rect, Color::WHITE, "1 Player", Some(one_player_callback));
+
+        let two_player_rect = Rect::from_center(Point::new(640, 416), 192, 96);
+        let two_player_callback = self.play_callback(PlayerMode::TwoPlayer);
+        button::create(&self.cmanager, "bvversus", two_player_rect, Color::WHITE, "2 Players", Some(two_player_callback));
 
-        let exit_rect = Rect::from_center(Point::new(640, 472), 192, 96);
-        let exit_callback = Box::new(|| {
+        let exit_rect = Rect::from_center(Point::new(640, 528), 192, 96);
+        let transitions = Rc::clone(&self.transitions);
+        let exit_callback = Box::new(move || {
             println!("Exit Game!");
-            util::set_play(false);
+            util::request_transition(&transitions, Transition::Quit);
         });
         button::create(&self.cmanager, "bexit", exit_rect, Color::WHITE, "Exit", Some(exit_callback));
+
+        // Cycles the mouse between no paddle, the left paddle and the right paddle
+        let mouse_rect = Rect::from_center(Point::new(1120, 656), 192, 64);
+        let setup = Rc::clone(&self.setup);
+        let mouse_callback = Box::new(move || {
+            let mut setup = setup.borrow_mut();
+            setup.mouse_side = match setup.mouse_side {
+                None => Some(Side::Left),
+                Some(Side::Left) => Some(Side::Right),
+                Some(Side::Right) => None
+            };
+        });
+        button::create(&self.cmanager, "bmouse", mouse_rect, Color::WHITE, "Mouse", Some(mouse_callback));
+    }
+
+    // Starts a match with the chosen players, Pong spawns its paddles to match on entering
+    fn play_callback(&self, mode: PlayerMode) -> Box<dyn Fn()> {
+        let transitions = Rc::clone(&self.transitions);
+        let setup = Rc::clone(&self.setup);
+
+        Box::new(move || {
+            println!("Play {:?}!", mode);
+            setup.borrow_mut().players = mode;
+            util::request_transition(&transitions, Transition::Replace(GameStates::Pong));
+        })
     }
 
     fn process_input(&mut self){
-        for event in &self.event_runner.borrow().event_list {
-            match event {
-                Event::Quit {..} |
-                Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
-                    util::set_play(false);
-                },
-                Event::KeyDown { keycode: Some(Keycode::P), .. } => {
-                    util::change_gamestate(GameStates::Pong);
-                },
-                _ => {}
+        let event_runner = self.event_runner.borrow();
+
+        for event in &event_runner.event_list {
+            if let Event::Quit {..} = event {
+                util::request_transition(&self.transitions, Transition::Quit);
             }
         }
+
+        if event_runner.actions.pressed(Action::Back) {
+            util::request_transition(&self.transitions, Transition::Quit);
+        }
+        // Confirm plays again with whichever mode was picked last
+        else if event_runner.actions.pressed(Action::Confirm) {
+            util::request_transition(&self.transitions, Transition::Replace(GameStates::Pong));
+        }
+    }
+
+    /*
+        The menu doubles as the controller lobby. Menu up and down pick the
+        number of players and menu left and right move the controller that
+        pressed them to the left or right paddle.
+    */
+    fn process_lobby_input(&mut self) {
+        let mut event_runner = self.event_runner.borrow_mut();
+
+        // Pads only, the arrow keys share these actions but never see the lobby
+        if !event_runner.actions.pressed_on_pads(Action::MenuUp).is_empty() {
+            self.setup.borrow_mut().players = PlayerMode::OnePlayer;
+        }
+        else if !event_runner.actions.pressed_on_pads(Action::MenuDown).is_empty() {
+            self.setup.borrow_mut().players = PlayerMode::TwoPlayer;
+        }
+
+        let mut assignments = Vec::new();
+        for (action, side) in [(Action::MenuLeft, Side::Left), (Action::MenuRight, Side::Right)] {
+            for id in event_runner.actions.pressed_on_pads(action) {
+                assignments.push((*id, side));
+            }
+        }
+
+        for (id, side) in assignments {
+            event_runner.controllers.assign(id, side);
+        }
+    }
+
+    fn render_mouse_side(&mut self) {
+        let label = match self.setup.borrow().mouse_side {
+            None => "Mouse: off",
+            Some(Side::Left) => "Mouse: left paddle",
+            Some(Side::Right) => "Mouse: right paddle"
+        };
+
+        self.font_manager.borrow_mut().render_text(label, Point::new(1120, 600), "arial", 20, &self.canvas, Color::WHITE);
+    }
+
+    // Only shown once a controller is plugged in, keyboard players never need it
+    fn render_lobby(&mut self) {
+        let assignments = self.event_runner.borrow().controllers.assignments();
+        if assignments.is_empty() {
+            return;
+        }
+
+        let mode = match self.setup.borrow().players {
+            PlayerMode::OnePlayer => "1 Player",
+            PlayerMode::TwoPlayer => "2 Players"
+        };
+        let hint = format!("{} - D-pad up/down for players, left/right for a side", mode);
+        let mut font_manager = self.font_manager.borrow_mut();
+        font_manager.render_text(&hint, Point::new(640, 610), "arial", 20, &self.canvas, Color::WHITE);
+
+        for (index, (name, side)) in assignments.iter().enumerate() {
+            let side = match side {
+                Some(Side::Left) => "left paddle",
+                Some(Side::Right) => "right paddle",
+                None => "not playing"
+            };
+            let line = format!("{}: {}", name, side);
+            let y = 640 + 26 * index as i32;
+            font_manager.render_text(&line, Point::new(640, y), "arial", 20, &self.canvas, Color::WHITE);
+        }
     }
 }
 
@@ -86,12 +190,17 @@ impl<'ttf> MainMenuState<'ttf> {
 */
 
 impl<'ttf> GameState for MainMenuState<'ttf> {
-    fn execute(&mut self, delta_time: f32) {
+    fn update(&mut self, delta_time: f32) {
         self.process_input();
-        self.button_input_system.execute(delta_time);
-        self.render_system.execute(delta_time);
-        self.text_render_system.execute(delta_time);
+        self.process_lobby_input();
+        self.schedule.run_stages(&Stage::UPDATE, delta_time);
+    }
+
+    fn render(&mut self, delta_time: f32) {
+        self.schedule.run_stage(Stage::Render, delta_time);
 
         self.font_manager.borrow_mut().render_text("Pong", Point::new(640, 100), "arial", 144, &self.canvas, Color::WHITE);
+        self.render_mouse_side();
+        self.render_lobby();
     }
 }
\ No newline at end of file
@@ -1,58 +1,71 @@
-use crate::components::{CMovement2D, CCollision2D};
-use crate::components::{CPaddleInfo, CTexture, CPosition2D};
+use crate::collision::Shape;
+use crate::components::{CMovement2D, CCollision2D, LAYER_NONE, LAYER_PADDLE};
+use crate::components::{CInputBinding, CPaddleInfo, CTexture, CPosition2D};
+use crate::events::Side;
+use crate::input::Action;
+use crate::resources::MatchConfig;
 use crate::util::CManagerRc;
+use crate::vec2::Vec2;
 use sdl2::pixels::Color;
-use sdl2::rect::Point;
-use std::time::Instant;
 
-const START_P1: (i32, i32) = (64, 360);
-const START_P2: (i32, i32) = (1216, 360);
+const START_P1: Vec2 = Vec2::new(64.0, 360.0);
+const START_P2: Vec2 = Vec2::new(1216.0, 360.0);
 
 pub enum Direction {
     Up,
     Down,
-    Stationary
+    Stationary,
+    // Fraction of full speed from a controller stick, negative is up
+    Analog(f32),
+    // Height to head for as fast as the paddle can, where the mouse is
+    Follow(f32)
 }
 
-pub fn create(cmanager: &CManagerRc, is_left: bool, is_ai: bool, name: &str) {
+// Who steers a paddle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaddleControl {
+    Ai,
+    // Bound keys, or the controller assigned to the paddle's side
+    Keys,
+    Mouse
+}
+
+pub fn create(cmanager: &CManagerRc, is_left: bool, control: PaddleControl, name: &str) {
     let mut cmanager = cmanager.borrow_mut();
-    let id = cmanager.id_allocator.get_number();
+    let id = cmanager.create_entity();
+    let config = cmanager.resource::<MatchConfig>().map(|config| config.clone()).unwrap_or_default();
 
     let collision_2d = CCollision2D {
         id,
         name: name.to_string(),
-        size: (10, 100)
+        size: Vec2::new(10.0, 100.0),
+        shape: Shape::Aabb,
+        layer: LAYER_PADDLE,
+        mask: LAYER_NONE,
+        // Paddles are steered by SysPaddleMovement, balls bounce off them
+        is_static: true
     };
     
     let movement_2d = CMovement2D {
         id,
         name: name.to_string(),
-        speed: 540.0,
+        speed: config.paddle_speed,
         x: 0.0,
         y: 0.0
     };
 
-    let position_2d: CPosition2D;
-
-    if is_left {
-        position_2d = CPosition2D {
-            id,
-            name: name.to_string(),
-            pos: Point::new(START_P1.0, START_P1.1)
-        };
+    let position_2d = if is_left {
+        CPosition2D::new(id, name, START_P1)
     }
     else {
-        position_2d = CPosition2D {
-            id,
-            name: name.to_string(),
-            pos: Point::new(START_P2.0, START_P2.1)
-        };
-    }
+        CPosition2D::new(id, name, START_P2)
+    };
 
     let texture = CTexture {
         id,
         name: name.to_string(),
         size: (10, 100),
+        shape: Shape::Aabb,
         color: Color::WHITE
     };
 
@@ -60,14 +73,27 @@ pub fn create(cmanager: &CManagerRc, is_left: bool, is_ai: bool, name: &str) {
         id,
         name: name.to_string(),
         direction: Direction::Stationary,
-        ai_delay: 125,
-        ai_delay_timer: Instant::now(),
-        is_ai
+        ai_delay: config.ai_delay,
+        ai_delay_elapsed: 0.0,
+        is_ai: control == PaddleControl::Ai
     };
-    
-    cmanager.ccollision_2d.insert(id, collision_2d);
-    cmanager.cmovement_2d.insert(id, movement_2d);
-    cmanager.cpaddle_info.insert(id, paddle_info);
-    cmanager.cposition_2d.insert(id, position_2d);
-    cmanager.ctexture.insert(id, texture);
+
+    // Human paddles answer to the move actions bound for their side
+    if control != PaddleControl::Ai {
+        let side = if is_left { Side::Left } else { Side::Right };
+        cmanager.insert(id, CInputBinding {
+            id,
+            name: name.to_string(),
+            side,
+            up: Action::MoveUp(side),
+            down: Action::MoveDown(side),
+            follows_mouse: control == PaddleControl::Mouse
+        });
+    }
+
+    cmanager.insert(id, collision_2d);
+    cmanager.insert(id, movement_2d);
+    cmanager.insert(id, paddle_info);
+    cmanager.insert(id, position_2d);
+    cmanager.insert(id, texture);
 }
\ No newline at end of file
@@ -0,0 +1,172 @@
+use crate::button;
+use crate::componentmanager::Entity;
+use crate::components::{CCollision2D, CPosition2D};
+use crate::gamestate::{GameState, GameStates, Transition};
+use crate::input::Action;
+use crate::events::ButtonClicked;
+use crate::schedule::{Schedule, Stage};
+use crate::systems::{SysRenderTexture, SysButtonCallback, SysButtonInput, SysRenderText};
+use crate::util::{CanvasRc, EventRc, FontRc, TransitionRc, self, CManagerRc};
+use crate::vec2::Vec2;
+use sdl2::pixels::Color;
+use sdl2::rect::{Rect, Point};
+use sdl2::render::BlendMode;
+use sdl2::event::Event;
+use std::rc::Rc;
+
+/*
+    Overlay pushed on top of the match. The match stays on the stack beneath
+    it and keeps being drawn, dimmed, but is not updated until this state pops.
+    Menu up and down move the focus between the buttons and confirm presses
+    the focused one, so a controller can reach all of them.
+*/
+pub struct PauseMenuState<'ttf> {
+    cmanager: CManagerRc,
+    schedule: Schedule<'ttf>,
+    canvas: CanvasRc,
+    event_runner: EventRc,
+    font_manager: FontRc<'ttf>,
+    transitions: TransitionRc,
+    // Top to bottom, the order menu up and down walk them in
+    buttons: Vec<Entity>,
+    focus: usize
+}
+
+impl<'ttf> PauseMenuState<'ttf> {
+    pub fn new(
+            canvas: &CanvasRc,
+            event_runner: &EventRc,
+            font_manager: &FontRc<'ttf>,
+            transitions: &TransitionRc) -> Self {
+        let cmanager: CManagerRc = util::create_component_manager();
+        cmanager.borrow_mut().add_event::<ButtonClicked>();
+
+        let mut schedule = Schedule::new(&cmanager);
+        schedule.add_system(Stage::Input, "button_input", SysButtonInput::new(&cmanager, event_runner));
+        schedule.add_system(Stage::Simulation, "button_callback", SysButtonCallback::new(&cmanager));
+        schedule.add_system(Stage::Render, "render_texture", SysRenderTexture::new(&cmanager, canvas));
+        schedule.add_system(Stage::Render, "render_text", SysRenderText::new(&cmanager, canvas, font_manager))
+            .after("render_texture");
+
+        let mut pms = Self {
+            cmanager,
+            schedule,
+            canvas: Rc::clone(canvas),
+            event_runner: Rc::clone(event_runner),
+            font_manager: Rc::clone(font_manager),
+            transitions: Rc::clone(transitions),
+            buttons: Vec::new(),
+            focus: 0
+        };
+
+        pms.init();
+
+        pms
+    }
+
+    fn init(&mut self) {
+        let resume_rect = Rect::from_center(Point::new(640, 320), 192, 96);
+        let transitions = Rc::clone(&self.transitions);
+        let resume_callback = Box::new(move || {
+            util::request_transition(&transitions, Transition::Pop);
+        });
+        let button = button::create(&self.cmanager, "bresume", resume_rect, Color::WHITE, "Resume", Some(resume_callback));
+        self.buttons.push(button);
+
+        let restart_rect = Rect::from_center(Point::new(640, 432), 192, 96);
+        let transitions = Rc::clone(&self.transitions);
+        let restart_callback = Box::new(move || {
+            util::request_transition(&transitions, Transition::Pop);
+            util::request_transition(&transitions, Transition::Restart(GameStates::Pong));
+        });
+        let button = button::create(&self.cmanager, "brestart", restart_rect, Color::WHITE, "Restart", Some(restart_callback));
+        self.buttons.push(button);
+
+        // Closes the overlay, then swaps the match for the main menu
+        let quit_rect = Rect::from_center(Point::new(640, 544), 192, 96);
+        let transitions = Rc::clone(&self.transitions);
+        let quit_callback = Box::new(move || {
+            util::request_transition(&transitions, Transition::Pop);
+            util::request_transition(&transitions, Transition::Replace(GameStates::MainMenu));
+        });
+        let button = button::create(&self.cmanager, "bquit", quit_rect, Color::WHITE, "Menu", Some(quit_callback));
+        self.buttons.push(button);
+    }
+
+    fn process_input(&mut self) {
+        let event_runner = self.event_runner.borrow();
+
+        for event in &event_runner.event_list {
+            if let Event::Quit {..} = event {
+                util::request_transition(&self.transitions, Transition::Quit);
+            }
+        }
+
+        let actions = &event_runner.actions;
+        if actions.pressed(Action::Pause) || actions.pressed(Action::Back) {
+            util::request_transition(&self.transitions, Transition::Pop);
+        }
+        else if actions.pressed(Action::Confirm) {
+            self.press_focused();
+        }
+        else if actions.pressed(Action::MenuUp) {
+            self.focus = (self.focus + self.buttons.len() - 1) % self.buttons.len();
+        }
+        else if actions.pressed(Action::MenuDown) {
+            self.focus = (self.focus + 1) % self.buttons.len();
+        }
+    }
+
+    // Clicks the focused button, its callback runs with the schedule like a mouse click's
+    fn press_focused(&self) {
+        self.cmanager.borrow().send_event(ButtonClicked { entity: self.buttons[self.focus] });
+    }
+
+    // Drawn under the buttons, a little larger than the focused one so its edge shows as a border
+    fn render_focus(&mut self) {
+        let cmanager = self.cmanager.borrow();
+        let button = self.buttons[self.focus];
+        let (Some(pos), Some(collision)) = (cmanager.get::<CPosition2D>(&button), cmanager.get::<CCollision2D>(&button)) else {
+            return;
+        };
+
+        let rect = util::centered_rect(pos.pos, collision.size + Vec2::new(12.0, 12.0));
+        let mut canvas = self.canvas.borrow_mut();
+        canvas.set_draw_color(Color::YELLOW);
+        canvas.fill_rect(rect).unwrap();
+    }
+
+    fn render_backdrop(&mut self) {
+        let (width, height) = self.canvas.borrow().window().size();
+        let mut canvas = self.canvas.borrow_mut();
+
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(Color::RGBA(0, 0, 0, 160));
+        canvas.fill_rect(Rect::new(0, 0, width, height)).unwrap();
+        canvas.set_blend_mode(BlendMode::None);
+    }
+}
+
+impl<'ttf> GameState for PauseMenuState<'ttf> {
+    fn update(&mut self, delta_time: f32) {
+        self.process_input();
+        self.schedule.run_stages(&Stage::UPDATE, delta_time);
+    }
+
+    fn render(&mut self, delta_time: f32) {
+        self.render_backdrop();
+        self.render_focus();
+        self.schedule.run_stage(Stage::Render, delta_time);
+
+        self.font_manager.borrow_mut().render_text("Paused", Point::new(640, 150), "arial", 144, &self.canvas, Color::WHITE);
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+
+    // Every pause starts on Resume
+    fn on_enter(&mut self) {
+        self.focus = 0;
+    }
+}
@@ -1,63 +1,189 @@
-use crate::util::{GameStateRc, EventRc, FontRc, PlayRc, CManagerRc, CanvasRc};
-use crate::{paddle, ball, util, GAME_STATE};
-use crate::gamestate::{GameState, GameStates};
-use crate::systems::{System, SysRenderTexture, SysBallMovement, SysPaddleMovement, SysScoring};
+use crate::util::{EventRc, FontRc, CManagerRc, CanvasRc, MatchSetupRc, SummaryRc, TransitionRc};
+use crate::{paddle, ball, util, wall};
+use crate::gamestate::{GameState, GameStates, Transition};
+use crate::input::Action;
+use crate::paddle::PaddleControl;
+use crate::events::{Contact, GoalScored, PaddleHit, Side};
+use crate::resources::{GameRng, Interpolation, MatchConfig, MatchSetup, MatchState, MatchSummary, PlayerMode, Score, WindowSize};
+use crate::schedule::{Schedule, Stage};
+use crate::systems::{SysRenderTexture, SysBallReset, SysBallSplit, SysPaddleBounce, SysPaddleAi, SysPaddleMovement, SysPaddleSteering, SysPhysics, SysMatchRules, SysScoreDisplay, SysScoring, SysSnapshotPositions, SysWindowSize};
+use crate::timestep::FixedTimestep;
 use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
 use std::rc::Rc;
 
 pub struct PongState<'a> {
-    render_system: SysRenderTexture,
-    ball_move_system: SysBallMovement,
-    paddle_move_system: SysPaddleMovement,
-    scoring_system: SysScoring<'a>,
+    cmanager: CManagerRc,
+    schedule: Schedule<'a>,
+    timestep: FixedTimestep,
     canvas: CanvasRc,
-    event_runner: EventRc
+    event_runner: EventRc,
+    transitions: TransitionRc,
+    summary: SummaryRc,
+    setup: MatchSetupRc
 }
 
 impl<'ttf> PongState<'ttf> {
     pub fn new(
             canvas: &CanvasRc,
             event_runner: &EventRc,
-            font_manager: &FontRc<'ttf>) -> Self {
+            font_manager: &FontRc<'ttf>,
+            transitions: &TransitionRc,
+            summary: &SummaryRc,
+            setup: &MatchSetupRc) -> Self {
         let cmanager: CManagerRc = util::create_component_manager();
+        let config = MatchConfig::default();
+        let timestep = FixedTimestep::new(config.tick_rate);
 
-        let srender = SysRenderTexture::new(&cmanager, canvas);
-        let sball_move = SysBallMovement::new(&cmanager, canvas);
-        let spaddle_move = SysPaddleMovement::new(&cmanager, event_runner, canvas);
-        let sscoring = SysScoring::new(&cmanager,canvas, font_manager);
+        {
+            let (width, height) = canvas.borrow().window().size();
+            let mut cmanager = cmanager.borrow_mut();
+            cmanager.insert_resource(Score::default());
+            cmanager.insert_resource(MatchState::default());
+            cmanager.insert_resource(WindowSize { width, height });
+            cmanager.insert_resource(config);
+            cmanager.insert_resource(Interpolation::default());
+            cmanager.insert_resource(GameRng::from_entropy());
+            cmanager.add_event::<GoalScored>();
+            cmanager.add_event::<PaddleHit>();
+            cmanager.add_event::<Contact>();
+        }
 
-        paddle::create(&cmanager, true, false, "paddle_1");
-        paddle::create(&cmanager, false, true, "paddle_2");
-        ball::create(&cmanager);
+        let mut schedule = Schedule::new(&cmanager);
+        schedule.add_system(Stage::Input, "window_size", SysWindowSize::new(&cmanager, canvas));
+        schedule.add_system(Stage::Input, "paddle_steering", SysPaddleSteering::new(&cmanager, event_runner));
+        schedule.add_system(Stage::Simulation, "snapshot_positions", SysSnapshotPositions::new(&cmanager))
+            .before("ball_reset");
+        schedule.add_system(Stage::Simulation, "ball_reset", SysBallReset::new(&cmanager))
+            .before("physics");
+        schedule.add_system(Stage::Simulation, "paddle_bounce", SysPaddleBounce::new(&cmanager))
+            .before("physics");
+        schedule.add_system(Stage::Simulation, "ball_split", SysBallSplit::new(&cmanager))
+            .before("physics")
+            .run_if(|cmanager| cmanager.resource::<MatchConfig>().is_some_and(|config| config.split_every > 0));
+        schedule.add_system(Stage::Simulation, "paddle_ai", SysPaddleAi::new(&cmanager))
+            .before("paddle_movement");
+        schedule.add_system(Stage::Simulation, "paddle_movement", SysPaddleMovement::new(&cmanager))
+            .after("snapshot_positions");
+        schedule.add_system(Stage::Simulation, "physics", SysPhysics::new(&cmanager))
+            .after("paddle_movement");
+        schedule.add_system(Stage::Scoring, "scoring", SysScoring::new(&cmanager));
+        schedule.add_system(Stage::Scoring, "match_rules", SysMatchRules::new(&cmanager))
+            .after("scoring");
+        schedule.add_system(Stage::Render, "score_display", SysScoreDisplay::new(&cmanager, canvas, font_manager));
+        schedule.add_system(Stage::Render, "render_texture", SysRenderTexture::new(&cmanager, canvas));
 
+        // Entities are spawned by reset when the state is entered
         Self {
-            render_system: srender,
-            ball_move_system: sball_move,
-            paddle_move_system: spaddle_move,
-            scoring_system: sscoring,
+            cmanager,
+            schedule,
+            timestep,
             canvas: Rc::clone(canvas),
             event_runner: Rc::clone(event_runner),
+            transitions: Rc::clone(transitions),
+            summary: Rc::clone(summary),
+            setup: Rc::clone(setup)
         }
     }
 }
 
 impl PongState<'_> {
+    fn spawn_entities(cmanager: &CManagerRc, setup: MatchSetup, controller_sides: &[Side]) {
+        let window = cmanager.borrow().resource::<WindowSize>().map(|window| *window);
+        if let Some(window) = window {
+            wall::create_bounds(cmanager, window.width, window.height);
+        }
+
+        let solo_side = setup.solo_side(controller_sides);
+        let control = |side| match setup.players {
+            PlayerMode::OnePlayer if side != solo_side => PaddleControl::Ai,
+            _ if setup.mouse_side == Some(side) => PaddleControl::Mouse,
+            _ => PaddleControl::Keys
+        };
+
+        paddle::create(cmanager, true, control(Side::Left), "paddle_1");
+        paddle::create(cmanager, false, control(Side::Right), "paddle_2");
+        ball::create_all(cmanager);
+    }
+
     fn process_input(&mut self) {
-        for event in &self.event_runner.borrow().event_list {
-            match event {
-                Event::Quit {..} |
-                Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
-                    util::change_gamestate(GameStates::MainMenu);
-                },
-                Event::KeyDown { keycode: Some(Keycode::P), .. } => {
-                    util::change_gamestate(GameStates::MainMenu);
-                },
-                _ => {}
+        let event_runner = self.event_runner.borrow();
+
+        for event in &event_runner.event_list {
+            if let Event::Quit {..} = event {
+                util::request_transition(&self.transitions, Transition::Quit);
             }
         }
+
+        if event_runner.actions.pressed(Action::Pause) {
+            util::request_transition(&self.transitions, Transition::Push(GameStates::PauseMenu));
+        }
+    }
+
+    /*
+        Puts the match back to kick-off without rebuilding the state. Every
+        entity is despawned and spawned fresh, and the score, the rules clock
+        and any events still in flight from the old match are cleared.
+    */
+    pub fn reset(&mut self) {
+        {
+            let mut cmanager = self.cmanager.borrow_mut();
+            let entities = cmanager.allocated().to_vec();
+
+            for entity in entities.iter() {
+                cmanager.free_entity(entity);
+            }
+
+            cmanager.clear_events();
+
+            if let Some(mut score) = cmanager.resource_mut::<Score>() {
+                score.reset();
+            };
+            if let Some(mut state) = cmanager.resource_mut::<MatchState>() {
+                state.reset();
+            };
+        }
+
+        *self.summary.borrow_mut() = None;
+        self.timestep.reset();
+        let controller_sides: Vec<Side> = self.event_runner.borrow().controllers.assignments()
+            .into_iter()
+            .filter_map(|(_, side)| side)
+            .collect();
+        Self::spawn_entities(&self.cmanager, *self.setup.borrow(), &controller_sides);
+    }
+
+    /*
+        Hands a settled match to the game over screen. The shared summary
+        doubles as the record that it has been shown, so it is only pushed
+        once per match.
+    */
+    fn check_match_over(&mut self) -> bool {
+        if self.summary.borrow().is_some() {
+            return true;
+        }
+
+        let summary = {
+            let cmanager = self.cmanager.borrow();
+            let result = cmanager.resource::<MatchState>().and_then(|state| state.result);
+            let score = cmanager.resource::<Score>().map(|score| *score).unwrap_or_default();
+            result.map(|result| MatchSummary { result, score })
+        };
+
+        if summary.is_none() {
+            return false;
+        }
+
+        *self.summary.borrow_mut() = summary;
+        util::request_transition(&self.transitions, Transition::Push(GameStates::GameOver));
+        true
+    }
+
+    // Movement systems stay off while paused so nothing drifts under the overlay
+    fn set_simulation_enabled(&mut self, enabled: bool) {
+        self.schedule.set_enabled("paddle_movement", enabled);
+        self.schedule.set_enabled("physics", enabled);
     }
 
     fn render_divider(&mut self) {
@@ -85,13 +211,49 @@ impl PongState<'_> {
 
 
 impl<'ttf> GameState for PongState<'ttf> {
-    fn execute(&mut self, delta_time: f32) {
+    /*
+        Input runs once per frame so no key presses are missed, then the
+        simulation is stepped in fixed ticks for however much time has built
+        up. The leftover fraction of a tick is handed to the render systems.
+    */
+    fn update(&mut self, delta_time: f32) {
         self.process_input();
-        self.paddle_move_system.execute(delta_time);
-        self.ball_move_system.execute(delta_time);
-        self.scoring_system.execute(delta_time);
-        self.render_system.execute(delta_time);
+        self.schedule.run_stage(Stage::Input, delta_time);
+
+        self.timestep.accumulate(delta_time);
+        while self.timestep.consume_tick() {
+            self.schedule.run_stages(&Stage::TICK, self.timestep.tick());
+
+            if self.check_match_over() {
+                break;
+            }
+        }
+
+        if let Some(mut interpolation) = self.cmanager.borrow().resource_mut::<Interpolation>() {
+            interpolation.alpha = self.timestep.alpha();
+        };
+    }
+
+    fn render(&mut self, delta_time: f32) {
+        self.schedule.run_stage(Stage::Render, delta_time);
 
         self.render_divider();
     }
+
+    fn on_pause(&mut self) {
+        self.set_simulation_enabled(false);
+    }
+
+    fn on_resume(&mut self) {
+        self.set_simulation_enabled(true);
+    }
+
+    // Coming in from the menu always starts a fresh match
+    fn on_enter(&mut self) {
+        self.reset();
+    }
+
+    fn restart(&mut self) {
+        self.reset();
+    }
 }
\ No newline at end of file
@@ -0,0 +1,353 @@
+use crate::componentmanager::{ComponentManager, Entity};
+use crate::storage::Components;
+use std::cell::{Ref, RefMut};
+use std::marker::PhantomData;
+
+/*
+    One term of a query. Storage is whatever the term keeps borrowed from the
+    ComponentManager for the life of the query, and fetch pulls the term's item
+    for a single entity out of it. Returning None from fetch skips the entity.
+
+    Terms can be tupled together, e.g. (&mut CPosition2D, &CMovement2D,
+    Option<&CTexture>, Without<CPaddleInfo>). Asking for the same component
+    type twice with one of them &mut panics, as with any RefCell.
+
+    Terms that require a component also offer that storage's dense entity list
+    to drive iteration. A query walks the shortest such list instead of every
+    allocated entity, and only falls back to the allocator when every term is
+    optional or a filter on absence.
+*/
+pub trait Fetch {
+    type Storage<'a>;
+    type Item<'b>;
+
+    fn borrow(cmanager: &ComponentManager) -> Self::Storage<'_>;
+    fn fetch<'b>(storage: &'b mut Self::Storage<'_>, entity: &Entity) -> Option<Self::Item<'b>>;
+
+    fn driver_len(_storage: &Self::Storage<'_>) -> Option<usize> {
+        None
+    }
+
+    // Only called when driver_len returned Some, with index below that length
+    fn driver_entity(_storage: &Self::Storage<'_>, _index: usize) -> Entity {
+        unreachable!("term does not drive iteration")
+    }
+}
+
+// Matches entities that have T without borrowing it out
+pub struct With<T>(PhantomData<T>);
+
+// Matches entities that do not have T
+pub struct Without<T>(PhantomData<T>);
+
+pub struct Query<'a, Q: Fetch> {
+    entities: &'a [Entity],
+    storage: Q::Storage<'a>
+}
+
+impl<'a, Q: Fetch> Query<'a, Q> {
+    pub fn new(cmanager: &'a ComponentManager) -> Self {
+        Self {
+            entities: cmanager.allocated(),
+            storage: Q::borrow(cmanager)
+        }
+    }
+
+    pub fn get(&mut self, entity: &Entity) -> Option<Q::Item<'_>> {
+        Q::fetch(&mut self.storage, entity)
+    }
+
+    pub fn for_each<F>(&mut self, mut f: F)
+    where
+        F: for<'b> FnMut(Entity, Q::Item<'b>)
+    {
+        match Q::driver_len(&self.storage) {
+            Some(len) => {
+                for index in 0..len {
+                    let entity = Q::driver_entity(&self.storage, index);
+                    if let Some(item) = Q::fetch(&mut self.storage, &entity) {
+                        f(entity, item);
+                    }
+                }
+            },
+            None => {
+                for entity in self.entities {
+                    if let Some(item) = Q::fetch(&mut self.storage, entity) {
+                        f(*entity, item);
+                    }
+                }
+            }
+        }
+    }
+
+    /*
+        Snapshot of the matching entities, for when the caller needs to let go
+        of the query's borrows before touching each entity.
+    */
+    pub fn entities(&mut self) -> Vec<Entity> {
+        let mut matched = Vec::new();
+        self.for_each(|entity, _| matched.push(entity));
+        matched
+    }
+}
+
+impl<T: 'static> Fetch for &T {
+    type Storage<'a> = Option<Ref<'a, Components<T>>>;
+    type Item<'b> = &'b T;
+
+    fn borrow(cmanager: &ComponentManager) -> Self::Storage<'_> {
+        cmanager.components::<T>()
+    }
+
+    fn fetch<'b>(storage: &'b mut Self::Storage<'_>, entity: &Entity) -> Option<Self::Item<'b>> {
+        storage.as_ref()?.get(entity)
+    }
+
+    fn driver_len(storage: &Self::Storage<'_>) -> Option<usize> {
+        Some(storage.as_ref().map_or(0, |components| components.len()))
+    }
+
+    fn driver_entity(storage: &Self::Storage<'_>, index: usize) -> Entity {
+        storage.as_ref().unwrap().entities()[index]
+    }
+}
+
+impl<T: 'static> Fetch for &mut T {
+    type Storage<'a> = Option<RefMut<'a, Components<T>>>;
+    type Item<'b> = &'b mut T;
+
+    fn borrow(cmanager: &ComponentManager) -> Self::Storage<'_> {
+        cmanager.components_mut::<T>()
+    }
+
+    fn fetch<'b>(storage: &'b mut Self::Storage<'_>, entity: &Entity) -> Option<Self::Item<'b>> {
+        storage.as_mut()?.get_mut(entity)
+    }
+
+    fn driver_len(storage: &Self::Storage<'_>) -> Option<usize> {
+        Some(storage.as_ref().map_or(0, |components| components.len()))
+    }
+
+    fn driver_entity(storage: &Self::Storage<'_>, index: usize) -> Entity {
+        storage.as_ref().unwrap().entities()[index]
+    }
+}
+
+impl<T: 'static> Fetch for Option<&T> {
+    type Storage<'a> = Option<Ref<'a, Components<T>>>;
+    type Item<'b> = Option<&'b T>;
+
+    fn borrow(cmanager: &ComponentManager) -> Self::Storage<'_> {
+        cmanager.components::<T>()
+    }
+
+    fn fetch<'b>(storage: &'b mut Self::Storage<'_>, entity: &Entity) -> Option<Self::Item<'b>> {
+        Some(storage.as_ref().and_then(|components| components.get(entity)))
+    }
+}
+
+impl<T: 'static> Fetch for Option<&mut T> {
+    type Storage<'a> = Option<RefMut<'a, Components<T>>>;
+    type Item<'b> = Option<&'b mut T>;
+
+    fn borrow(cmanager: &ComponentManager) -> Self::Storage<'_> {
+        cmanager.components_mut::<T>()
+    }
+
+    fn fetch<'b>(storage: &'b mut Self::Storage<'_>, entity: &Entity) -> Option<Self::Item<'b>> {
+        Some(storage.as_mut().and_then(|components| components.get_mut(entity)))
+    }
+}
+
+impl<T: 'static> Fetch for With<T> {
+    type Storage<'a> = Option<Ref<'a, Components<T>>>;
+    type Item<'b> = ();
+
+    fn borrow(cmanager: &ComponentManager) -> Self::Storage<'_> {
+        cmanager.components::<T>()
+    }
+
+    fn fetch<'b>(storage: &'b mut Self::Storage<'_>, entity: &Entity) -> Option<Self::Item<'b>> {
+        storage.as_ref()?.contains_key(entity).then_some(())
+    }
+
+    fn driver_len(storage: &Self::Storage<'_>) -> Option<usize> {
+        Some(storage.as_ref().map_or(0, |components| components.len()))
+    }
+
+    fn driver_entity(storage: &Self::Storage<'_>, index: usize) -> Entity {
+        storage.as_ref().unwrap().entities()[index]
+    }
+}
+
+impl<T: 'static> Fetch for Without<T> {
+    type Storage<'a> = Option<Ref<'a, Components<T>>>;
+    type Item<'b> = ();
+
+    fn borrow(cmanager: &ComponentManager) -> Self::Storage<'_> {
+        cmanager.components::<T>()
+    }
+
+    fn fetch<'b>(storage: &'b mut Self::Storage<'_>, entity: &Entity) -> Option<Self::Item<'b>> {
+        let has = storage.as_ref().is_some_and(|components| components.contains_key(entity));
+        (!has).then_some(())
+    }
+}
+
+macro_rules! impl_fetch_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: Fetch),+> Fetch for ($($name,)+) {
+            type Storage<'a> = ($($name::Storage<'a>,)+);
+            type Item<'b> = ($($name::Item<'b>,)+);
+
+            fn borrow(cmanager: &ComponentManager) -> Self::Storage<'_> {
+                ($($name::borrow(cmanager),)+)
+            }
+
+            #[allow(non_snake_case)]
+            fn fetch<'b>(storage: &'b mut Self::Storage<'_>, entity: &Entity) -> Option<Self::Item<'b>> {
+                let ($($name,)+) = storage;
+                Some(($($name::fetch($name, entity)?,)+))
+            }
+
+            #[allow(non_snake_case)]
+            fn driver_len(storage: &Self::Storage<'_>) -> Option<usize> {
+                let ($($name,)+) = storage;
+                let mut shortest: Option<usize> = None;
+                $(
+                    if let Some(len) = $name::driver_len($name) {
+                        shortest = Some(shortest.map_or(len, |shortest| shortest.min(len)));
+                    }
+                )+
+                shortest
+            }
+
+            #[allow(non_snake_case)]
+            fn driver_entity(storage: &Self::Storage<'_>, index: usize) -> Entity {
+                let shortest = Self::driver_len(storage);
+                let ($($name,)+) = storage;
+                $(
+                    if shortest.is_some() && $name::driver_len($name) == shortest {
+                        return $name::driver_entity($name, index);
+                    }
+                )+
+                unreachable!("tuple has no driving term")
+            }
+        }
+    };
+}
+
+impl_fetch_tuple!(A);
+impl_fetch_tuple!(A, B);
+impl_fetch_tuple!(A, B, C);
+impl_fetch_tuple!(A, B, C, D);
+impl_fetch_tuple!(A, B, C, D, E);
+impl_fetch_tuple!(A, B, C, D, E, F);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Position(i32);
+    struct Speed(i32);
+    struct Frozen;
+    struct Missing;
+
+    // Five entities, all with a position, speeds added to the fourth and second in that order
+    fn world() -> (ComponentManager, Vec<Entity>) {
+        let mut cmanager = ComponentManager::default();
+        let entities: Vec<Entity> = (0..5).map(|_| cmanager.create_entity()).collect();
+
+        for (index, entity) in entities.iter().enumerate() {
+            cmanager.insert(*entity, Position(index as i32));
+        }
+        cmanager.insert(entities[3], Speed(30));
+        cmanager.insert(entities[1], Speed(10));
+        cmanager.insert(entities[4], Frozen);
+
+        (cmanager, entities)
+    }
+
+    #[test]
+    fn required_terms_match_entities_with_every_component() {
+        let (cmanager, entities) = world();
+        let mut matched = Vec::new();
+
+        cmanager.query::<(&Position, &Speed)>().for_each(|entity, (position, speed)| {
+            matched.push((entity, position.0, speed.0));
+        });
+
+        assert_eq!(matched, vec![(entities[3], 3, 30), (entities[1], 1, 10)]);
+    }
+
+    // Walking speeds' dense order rather than positions' shows which storage drove the query
+    #[test]
+    fn shortest_storage_drives_iteration() {
+        let (cmanager, entities) = world();
+
+        assert_eq!(cmanager.query::<(&Position, &Speed)>().entities(), vec![entities[3], entities[1]]);
+        assert_eq!(cmanager.query::<(&Speed, &Position)>().entities(), vec![entities[3], entities[1]]);
+        assert_eq!(cmanager.query::<(&Position, With<Frozen>)>().entities(), vec![entities[4]]);
+    }
+
+    #[test]
+    fn optional_terms_match_either_way() {
+        let (cmanager, entities) = world();
+
+        cmanager.query::<(&Position, Option<&mut Speed>)>().for_each(|_, (position, speed)| {
+            if let Some(speed) = speed {
+                speed.0 += position.0;
+            }
+        });
+
+        let speeds: Vec<Option<i32>> = entities.iter()
+            .map(|entity| cmanager.query::<Option<&Speed>>().get(entity).unwrap().map(|speed| speed.0))
+            .collect();
+        assert_eq!(speeds, vec![None, Some(11), None, Some(33), None]);
+    }
+
+    #[test]
+    fn with_and_without_filter_on_presence() {
+        let (cmanager, entities) = world();
+
+        assert_eq!(cmanager.query::<(&Position, With<Speed>, Without<Frozen>)>().entities(), vec![entities[3], entities[1]]);
+        assert_eq!(cmanager.query::<(&Position, Without<Speed>)>().entities(), vec![entities[0], entities[2], entities[4]]);
+    }
+
+    #[test]
+    fn only_optional_and_without_terms_fall_back_to_every_entity() {
+        let (cmanager, entities) = world();
+
+        let mut matched = Vec::new();
+        cmanager.query::<(Option<&Speed>, Without<Frozen>)>().for_each(|entity, (speed, _)| {
+            matched.push((entity, speed.is_some()));
+        });
+
+        assert_eq!(matched, vec![
+            (entities[0], false),
+            (entities[1], true),
+            (entities[2], false),
+            (entities[3], true)
+        ]);
+    }
+
+    #[test]
+    fn unregistered_storage_matches_nothing_or_everything() {
+        let (cmanager, entities) = world();
+
+        assert!(cmanager.query::<&Missing>().entities().is_empty());
+        assert!(cmanager.query::<(&Position, &mut Missing)>().entities().is_empty());
+        assert!(cmanager.query::<With<Missing>>().entities().is_empty());
+        assert_eq!(cmanager.query::<Without<Missing>>().entities(), entities);
+        assert_eq!(cmanager.query::<(&Position, Option<&Missing>)>().entities(), entities);
+    }
+
+    #[test]
+    fn get_looks_up_one_entity() {
+        let (cmanager, entities) = world();
+        let mut query = cmanager.query::<(&Position, &Speed)>();
+
+        assert_eq!(query.get(&entities[1]).map(|(position, speed)| (position.0, speed.0)), Some((1, 10)));
+        assert!(query.get(&entities[0]).is_none());
+    }
+}
@@ -0,0 +1,274 @@
+use crate::events::Side;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::cmp::Ordering;
+
+/*
+    World-wide singletons stored on the ComponentManager alongside the
+    component storages. Systems read and write them through resource and
+    resource_mut instead of keeping the state privately.
+*/
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Score {
+    pub p1: u8,
+    pub p2: u8
+}
+
+impl Score {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    // Saturates, an endless match with no target can outlast a u8
+    pub fn award(&mut self, side: Side) {
+        let points = match side {
+            Side::Left => &mut self.p1,
+            Side::Right => &mut self.p2
+        };
+        *points = points.saturating_add(1);
+    }
+}
+
+// Drawable area of the window, refreshed from the canvas once per frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowSize {
+    pub width: u32,
+    pub height: u32
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchConfig {
+    // 0 plays without a points target, leaving only the time limit
+    pub points_to_win: u8,
+    // Reaching points_to_win only wins with a two point lead
+    pub win_by_two: bool,
+    // Seconds of play, after which the leader wins
+    pub time_limit: Option<f32>,
+    // Level at the time limit plays on until the next goal instead of drawing
+    pub sudden_death: bool,
+    // Balls served at the start of the match and kept in play after goals
+    pub ball_count: u8,
+    // Splits never take the number of balls past this
+    pub max_balls: u8,
+    // A ball splits in two every this many paddle hits, 0 turns splitting off
+    pub split_every: u32,
+    pub ball_speed: f32,
+    // Added to the ball's speed on every paddle hit, up to max_ball_speed
+    pub ball_speed_increase: f32,
+    pub max_ball_speed: f32,
+    // Steepest return, in radians, for a ball striking the very end of a paddle
+    pub max_bounce_angle: f32,
+    pub paddle_speed: f32,
+    // Seconds of simulation time the AI waits between changes of direction
+    pub ai_delay: f32,
+    // Simulation ticks per second, independent of the display rate
+    pub tick_rate: u32
+}
+
+impl Default for MatchConfig {
+    fn default() -> Self {
+        Self {
+            points_to_win: 5,
+            win_by_two: false,
+            time_limit: None,
+            sudden_death: true,
+            ball_count: 1,
+            max_balls: 4,
+            split_every: 0,
+            ball_speed: 540.0,
+            ball_speed_increase: 30.0,
+            max_ball_speed: 1080.0,
+            max_bounce_angle: std::f32::consts::FRAC_PI_3,
+            paddle_speed: 540.0,
+            ai_delay: 0.125,
+            tick_rate: 120
+        }
+    }
+}
+
+impl MatchConfig {
+    // Applies the match rules to the score so far, None while play goes on
+    pub fn result(&self, score: &Score, elapsed: f32) -> Option<MatchResult> {
+        let leader = match score.p1.cmp(&score.p2) {
+            Ordering::Greater => Some(Side::Left),
+            Ordering::Less => Some(Side::Right),
+            Ordering::Equal => None
+        };
+
+        let lead_needed = if self.win_by_two { 2 } else { 1 };
+        let target_reached = self.points_to_win > 0 && score.p1.max(score.p2) >= self.points_to_win;
+
+        if target_reached && score.p1.abs_diff(score.p2) >= lead_needed {
+            return leader.map(MatchResult::Winner);
+        }
+
+        let time_up = self.time_limit.is_some_and(|limit| elapsed >= limit);
+        if !time_up {
+            return None;
+        }
+
+        match leader {
+            Some(side) => Some(MatchResult::Winner(side)),
+            None if self.sudden_death => None,
+            None => Some(MatchResult::Draw)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchResult {
+    Winner(Side),
+    Draw
+}
+
+// How a finished match ended, handed from the match to the game over screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MatchSummary {
+    pub result: MatchResult,
+    pub score: Score
+}
+
+// Progress of the match in play, cleared when it restarts
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct MatchState {
+    // Seconds of simulated play, paused time doesn't count
+    pub elapsed: f32,
+    pub result: Option<MatchResult>
+}
+
+impl MatchState {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/*
+    How far the current frame sits between the previous simulation tick and
+    the latest one. Render systems blend positions by it so motion stays
+    smooth when the tick rate and display rate differ.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interpolation {
+    pub alpha: f32
+}
+
+impl Default for Interpolation {
+    fn default() -> Self {
+        Self {
+            alpha: 1.0
+        }
+    }
+}
+
+// Who is at the paddles, picked on the main menu before a match starts
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerMode {
+    // One player against the AI, on whichever side MatchSetup::solo_side picks
+    #[default]
+    OnePlayer,
+    // Two people sharing the keyboard
+    TwoPlayer
+}
+
+// Lobby choices the next match is set up from
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MatchSetup {
+    pub players: PlayerMode,
+    // Paddle that follows the mouse instead of its keys, if any
+    pub mouse_side: Option<Side>
+}
+
+impl MatchSetup {
+    /*
+        Side the lone player takes in a one player match, the AI gets the
+        other. The mouse's side if it has one, otherwise the right if a
+        controller was moved there and none plays the left, otherwise the left.
+    */
+    pub fn solo_side(&self, controller_sides: &[Side]) -> Side {
+        if let Some(side) = self.mouse_side {
+            return side;
+        }
+
+        match controller_sides.contains(&Side::Right) && !controller_sides.contains(&Side::Left) {
+            true => Side::Right,
+            false => Side::Left
+        }
+    }
+}
+
+// Shared RNG every random draw in a match comes from
+pub struct GameRng(pub StdRng);
+
+impl GameRng {
+    pub fn from_entropy() -> Self {
+        Self(StdRng::from_entropy())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(p1: u8, p2: u8) -> Score {
+        Score { p1, p2 }
+    }
+
+    #[test]
+    fn points_go_to_the_scoring_side_and_stop_at_the_top() {
+        let mut points = score(3, u8::MAX);
+
+        points.award(Side::Left);
+        points.award(Side::Right);
+
+        assert_eq!(points, score(4, u8::MAX));
+    }
+
+    #[test]
+    fn first_to_the_target_wins() {
+        let config = MatchConfig::default();
+
+        assert_eq!(config.result(&score(4, 4), 0.0), None);
+        assert_eq!(config.result(&score(5, 4), 0.0), Some(MatchResult::Winner(Side::Left)));
+        assert_eq!(config.result(&score(2, 5), 0.0), Some(MatchResult::Winner(Side::Right)));
+    }
+
+    #[test]
+    fn win_by_two_plays_on_past_the_target() {
+        let config = MatchConfig { win_by_two: true, ..MatchConfig::default() };
+
+        assert_eq!(config.result(&score(5, 4), 0.0), None);
+        assert_eq!(config.result(&score(6, 5), 0.0), None);
+        assert_eq!(config.result(&score(5, 7), 0.0), Some(MatchResult::Winner(Side::Right)));
+    }
+
+    #[test]
+    fn time_limit_hands_the_match_to_the_leader() {
+        let config = MatchConfig { points_to_win: 0, time_limit: Some(60.0), ..MatchConfig::default() };
+
+        assert_eq!(config.result(&score(9, 3), 59.9), None);
+        assert_eq!(config.result(&score(9, 3), 60.0), Some(MatchResult::Winner(Side::Left)));
+        assert_eq!(config.result(&score(1, 2), 61.0), Some(MatchResult::Winner(Side::Right)));
+    }
+
+    #[test]
+    fn level_at_the_time_limit_goes_to_sudden_death_or_a_draw() {
+        let sudden_death = MatchConfig { time_limit: Some(60.0), sudden_death: true, ..MatchConfig::default() };
+        let draw = MatchConfig { sudden_death: false, ..sudden_death.clone() };
+
+        assert_eq!(sudden_death.result(&score(2, 2), 60.0), None);
+        assert_eq!(sudden_death.result(&score(3, 2), 75.0), Some(MatchResult::Winner(Side::Left)));
+        assert_eq!(draw.result(&score(2, 2), 60.0), Some(MatchResult::Draw));
+    }
+
+    #[test]
+    fn solo_player_takes_the_mouse_side_then_their_controller_side() {
+        let setup = MatchSetup::default();
+        assert_eq!(setup.solo_side(&[]), Side::Left);
+        assert_eq!(setup.solo_side(&[Side::Right]), Side::Right);
+        assert_eq!(setup.solo_side(&[Side::Right, Side::Left]), Side::Left);
+
+        let mouse_right = MatchSetup { mouse_side: Some(Side::Right), ..setup };
+        assert_eq!(mouse_right.solo_side(&[Side::Left]), Side::Right);
+    }
+}
@@ -0,0 +1,300 @@
+use crate::componentmanager::ComponentManager;
+use crate::systems::System;
+use crate::util::CManagerRc;
+use std::collections::hash_map::HashMap;
+use std::rc::Rc;
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, PartialOrd, Ord)]
+pub enum Stage {
+    Input,
+    Simulation,
+    Scoring,
+    Render
+}
+
+impl Stage {
+    pub const ALL: [Stage; 4] = [Stage::Input, Stage::Simulation, Stage::Scoring, Stage::Render];
+
+    // Everything but drawing, for states that render separately from updating
+    pub const UPDATE: [Stage; 3] = [Stage::Input, Stage::Simulation, Stage::Scoring];
+
+    // The stages stepped once per fixed tick rather than once per frame
+    pub const TICK: [Stage; 2] = [Stage::Simulation, Stage::Scoring];
+}
+
+pub type RunCondition = Box<dyn Fn(&ComponentManager) -> bool>;
+
+struct ScheduledSystem<'a> {
+    name: String,
+    stage: Stage,
+    system: Box<dyn System + 'a>,
+    before: Vec<String>,
+    after: Vec<String>,
+    run_condition: Option<RunCondition>,
+    enabled: bool
+}
+
+/*
+    Owns a state's systems and runs them stage by stage. Within a stage,
+    systems run in registration order unless before/after constraints say
+    otherwise. Constraints naming a system in another stage must agree with
+    the stage order, since stages always run Input -> Simulation -> Scoring ->
+    Render. Commands queued by systems are applied and event channels are
+    advanced after every stage.
+*/
+pub struct Schedule<'a> {
+    cmanager: CManagerRc,
+    systems: Vec<ScheduledSystem<'a>>,
+    order: HashMap<Stage, Vec<usize>>,
+    dirty: bool
+}
+
+/*
+    Returned by add_system so ordering and run conditions can be chained onto
+    the registration.
+*/
+pub struct SystemConfig<'s, 'a> {
+    entry: &'s mut ScheduledSystem<'a>
+}
+
+impl SystemConfig<'_, '_> {
+    pub fn before(self, name: &str) -> Self {
+        self.entry.before.push(name.to_string());
+        self
+    }
+
+    pub fn after(self, name: &str) -> Self {
+        self.entry.after.push(name.to_string());
+        self
+    }
+
+    pub fn run_if<F: Fn(&ComponentManager) -> bool + 'static>(self, condition: F) -> Self {
+        self.entry.run_condition = Some(Box::new(condition));
+        self
+    }
+}
+
+impl<'a> Schedule<'a> {
+    pub fn new(cmanager: &CManagerRc) -> Self {
+        Self {
+            cmanager: Rc::clone(cmanager),
+            systems: Vec::new(),
+            order: HashMap::new(),
+            dirty: false
+        }
+    }
+
+    pub fn add_system<S: System + 'a>(&mut self, stage: Stage, name: &str, system: S) -> SystemConfig<'_, 'a> {
+        if self.systems.iter().any(|entry| entry.name == name) {
+            panic!("System {} is already scheduled.", name);
+        }
+
+        self.systems.push(ScheduledSystem {
+            name: name.to_string(),
+            stage,
+            system: Box::new(system),
+            before: Vec::new(),
+            after: Vec::new(),
+            run_condition: None,
+            enabled: true
+        });
+        self.dirty = true;
+
+        SystemConfig { entry: self.systems.last_mut().unwrap() }
+    }
+
+    // Returns false if no system is registered under that name
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self.systems.iter_mut().find(|entry| entry.name == name) {
+            Some(entry) => {
+                entry.enabled = enabled;
+                true
+            },
+            None => false
+        }
+    }
+
+    pub fn run_stages(&mut self, stages: &[Stage], delta_time: f32) {
+        for &stage in stages {
+            self.run_stage(stage, delta_time);
+        }
+    }
+
+    pub fn run_stage(&mut self, stage: Stage, delta_time: f32) {
+        if self.dirty {
+            self.build_order();
+        }
+
+        self.cmanager.borrow_mut().begin_event_reads(stage as usize);
+
+        for &index in self.order.get(&stage).into_iter().flatten() {
+            let entry = &mut self.systems[index];
+            if !entry.enabled {
+                continue;
+            }
+
+            // The condition's borrow has to end before the system takes its own
+            let should_run = entry.run_condition.as_ref().is_none_or(|condition| condition(&self.cmanager.borrow()));
+            if should_run {
+                entry.system.execute(delta_time);
+            }
+        }
+
+        let mut cmanager = self.cmanager.borrow_mut();
+        cmanager.apply_commands();
+        cmanager.update_events();
+    }
+
+    /*
+        Topologically sorts each stage, breaking ties by registration order so
+        unconstrained systems keep the order they were added in.
+    */
+    fn build_order(&mut self) {
+        let index_of: HashMap<&str, usize> = self.systems.iter()
+            .enumerate()
+            .map(|(index, entry)| (entry.name.as_str(), index))
+            .collect();
+
+        let mut edges: Vec<Vec<usize>> = vec![Vec::new(); self.systems.len()];
+        let mut incoming = vec![0; self.systems.len()];
+
+        for (index, entry) in self.systems.iter().enumerate() {
+            let constraints = entry.before.iter().map(|name| (name, true))
+                .chain(entry.after.iter().map(|name| (name, false)));
+
+            for (name, is_before) in constraints {
+                let Some(&other) = index_of.get(name.as_str()) else {
+                    panic!("System {} is ordered against unknown system {}.", entry.name, name);
+                };
+
+                let (first, second) = if is_before { (index, other) } else { (other, index) };
+                let (first_stage, second_stage) = (self.systems[first].stage, self.systems[second].stage);
+
+                if first_stage > second_stage {
+                    panic!("System {} cannot run before {} from a later stage.", self.systems[first].name, self.systems[second].name);
+                }
+                else if first_stage == second_stage {
+                    edges[first].push(second);
+                    incoming[second] += 1;
+                }
+            }
+        }
+
+        self.order.clear();
+
+        for stage in Stage::ALL {
+            let members: Vec<usize> = (0..self.systems.len())
+                .filter(|&index| self.systems[index].stage == stage)
+                .collect();
+
+            let mut sorted = Vec::with_capacity(members.len());
+
+            while sorted.len() < members.len() {
+                let next = members.iter()
+                    .copied()
+                    .find(|&index| incoming[index] == 0 && !sorted.contains(&index));
+
+                let Some(next) = next else {
+                    panic!("Systems in stage {:?} have cyclic ordering constraints.", stage);
+                };
+
+                for &after in &edges[next] {
+                    incoming[after] -= 1;
+                }
+                sorted.push(next);
+            }
+
+            self.order.insert(stage, sorted);
+        }
+
+        self.dirty = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util;
+    use std::cell::RefCell;
+
+    type Log = Rc<RefCell<Vec<&'static str>>>;
+
+    struct Record {
+        name: &'static str,
+        log: Log
+    }
+
+    impl System for Record {
+        fn execute(&mut self, _delta_time: f32) {
+            self.log.borrow_mut().push(self.name);
+        }
+    }
+
+    fn add<'s, 'a>(schedule: &'s mut Schedule<'a>, log: &Log, stage: Stage, name: &'static str) -> SystemConfig<'s, 'a> {
+        schedule.add_system(stage, name, Record { name, log: Rc::clone(log) })
+    }
+
+    #[test]
+    fn constraints_reorder_and_everything_else_keeps_registration_order() {
+        let log = Log::default();
+        let mut schedule = Schedule::new(&util::create_component_manager());
+        add(&mut schedule, &log, Stage::Render, "draw");
+        add(&mut schedule, &log, Stage::Simulation, "a");
+        add(&mut schedule, &log, Stage::Simulation, "b");
+        add(&mut schedule, &log, Stage::Simulation, "c").before("a");
+        add(&mut schedule, &log, Stage::Simulation, "d").after("b");
+        add(&mut schedule, &log, Stage::Input, "read").before("draw");
+
+        schedule.run_stages(&Stage::ALL, 0.0);
+
+        assert_eq!(*log.borrow(), vec!["read", "b", "c", "a", "d", "draw"]);
+    }
+
+    #[test]
+    fn disabled_and_conditioned_systems_are_skipped() {
+        let log = Log::default();
+        let mut schedule = Schedule::new(&util::create_component_manager());
+        add(&mut schedule, &log, Stage::Simulation, "off");
+        add(&mut schedule, &log, Stage::Simulation, "never").run_if(|_| false);
+        add(&mut schedule, &log, Stage::Simulation, "on");
+        assert!(schedule.set_enabled("off", false));
+        assert!(!schedule.set_enabled("missing", false));
+
+        schedule.run_stages(&Stage::ALL, 0.0);
+
+        assert_eq!(*log.borrow(), vec!["on"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "cyclic ordering constraints")]
+    fn cycle_panics() {
+        let log = Log::default();
+        let mut schedule = Schedule::new(&util::create_component_manager());
+        add(&mut schedule, &log, Stage::Simulation, "a").before("b");
+        add(&mut schedule, &log, Stage::Simulation, "b").before("c");
+        add(&mut schedule, &log, Stage::Simulation, "c").before("a");
+
+        schedule.run_stages(&Stage::ALL, 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "from a later stage")]
+    fn ordering_against_the_stage_order_panics() {
+        let log = Log::default();
+        let mut schedule = Schedule::new(&util::create_component_manager());
+        add(&mut schedule, &log, Stage::Input, "read");
+        add(&mut schedule, &log, Stage::Render, "draw").before("read");
+
+        schedule.run_stages(&Stage::ALL, 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown system")]
+    fn ordering_against_an_unknown_system_panics() {
+        let log = Log::default();
+        let mut schedule = Schedule::new(&util::create_component_manager());
+        add(&mut schedule, &log, Stage::Input, "read").after("missing");
+
+        schedule.run_stages(&Stage::ALL, 0.0);
+    }
+}
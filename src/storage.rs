@@ -0,0 +1,141 @@
+use crate::componentmanager::Entity;
+
+/*
+    Sparse set keyed by entity. Components are packed into a dense Vec so
+    iterating a storage walks contiguous memory, while the sparse Vec maps an
+    entity's slot index straight to its dense index without hashing.
+
+    The dense side keeps the full handle, so a stale handle whose slot has been
+    reused is rejected by comparing generations.
+*/
+pub struct Components<T> {
+    sparse: Vec<Option<usize>>,
+    entities: Vec<Entity>,
+    values: Vec<T>
+}
+
+impl<T> Default for Components<T> {
+    fn default() -> Self {
+        Self {
+            sparse: Vec::new(),
+            entities: Vec::new(),
+            values: Vec::new()
+        }
+    }
+}
+
+impl<T> Components<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    fn dense_index(&self, entity: &Entity) -> Option<usize> {
+        let index = (*self.sparse.get(entity.index())?)?;
+        (self.entities[index] == *entity).then_some(index)
+    }
+
+    pub fn contains_key(&self, entity: &Entity) -> bool {
+        self.dense_index(entity).is_some()
+    }
+
+    pub fn get(&self, entity: &Entity) -> Option<&T> {
+        let index = self.dense_index(entity)?;
+        Some(&self.values[index])
+    }
+
+    pub fn get_mut(&mut self, entity: &Entity) -> Option<&mut T> {
+        let index = self.dense_index(entity)?;
+        Some(&mut self.values[index])
+    }
+
+    /*
+        Inserting for a handle whose slot is held by an older generation
+        replaces that stale component, returning it.
+    */
+    pub fn insert(&mut self, entity: Entity, value: T) -> Option<T> {
+        if entity.index() >= self.sparse.len() {
+            self.sparse.resize(entity.index() + 1, None);
+        }
+
+        if let Some(index) = self.sparse[entity.index()] {
+            self.entities[index] = entity;
+            return Some(std::mem::replace(&mut self.values[index], value));
+        }
+
+        self.sparse[entity.index()] = Some(self.values.len());
+        self.entities.push(entity);
+        self.values.push(value);
+        None
+    }
+
+    // Swap-removes, so the last component moves into the freed dense slot
+    pub fn remove(&mut self, entity: &Entity) -> Option<T> {
+        let index = self.dense_index(entity)?;
+
+        self.sparse[entity.index()] = None;
+        self.entities.swap_remove(index);
+        let value = self.values.swap_remove(index);
+
+        if let Some(moved) = self.entities.get(index) {
+            self.sparse[moved.index()] = Some(index);
+        }
+
+        Some(value)
+    }
+
+    // Entities in dense order, the order their components are stored in
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::componentmanager::IDAllocator;
+
+    #[test]
+    fn remove_moves_the_last_component_into_the_gap() {
+        let mut allocator = IDAllocator::default();
+        let [a, b, c] = [(); 3].map(|_| allocator.get_number());
+        let mut components = Components::new();
+        components.insert(a, "a");
+        components.insert(b, "b");
+        components.insert(c, "c");
+
+        assert_eq!(components.remove(&a), Some("a"));
+        assert_eq!(components.entities(), &[c, b]);
+        assert_eq!(components.get(&c), Some(&"c"));
+        assert_eq!(components.get(&b), Some(&"b"));
+        assert_eq!(components.get(&a), None);
+
+        // The moved entity's sparse entry points at its new dense slot
+        assert_eq!(components.remove(&c), Some("c"));
+        assert_eq!(components.entities(), &[b]);
+        assert_eq!(components.remove(&c), None);
+        assert_eq!(components.len(), 1);
+    }
+
+    #[test]
+    fn stale_handle_misses_and_is_replaced_by_the_new_occupant() {
+        let mut allocator = IDAllocator::default();
+        let old = allocator.get_number();
+        let mut components = Components::new();
+        components.insert(old, 1);
+
+        allocator.free_number(&old);
+        let new = allocator.get_number();
+        assert_eq!(components.get(&new), None);
+        assert!(!components.contains_key(&new));
+
+        assert_eq!(components.insert(new, 2), Some(1));
+        assert_eq!(components.get(&new), Some(&2));
+        assert_eq!(components.get(&old), None);
+        assert_eq!(components.remove(&old), None);
+        assert_eq!(components.len(), 1);
+    }
+}
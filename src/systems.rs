@@ -1,28 +1,65 @@
-use crate::{ball, util};
+use crate::{ball, collision, util};
+use crate::broadphase::SpatialGrid;
+use crate::collision::{RoundedBox, Shape};
 use crate::paddle::Direction;
-use crate::util::{CanvasRc, EventRc, FontRc, CManagerRc, GameStateRc};
+use crate::componentmanager::{ComponentManager, Entity};
+use crate::components::{CBall, CButtonInfo, CCollision2D, CInputBinding, CMovement2D, CPaddleInfo, CPosition2D, CText, CTexture};
+use crate::events::{ButtonClicked, Contact, GoalScored, PaddleHit, Side};
+use crate::resources::{Interpolation, MatchConfig, MatchState, Score, WindowSize};
+use crate::util::{CanvasRc, EventRc, FontRc, CManagerRc};
+use crate::query::{With, Without};
+use crate::vec2::Vec2;
 use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
 use sdl2::mouse::MouseButton;
 use sdl2::pixels::Color;
 use sdl2::rect::Point;
 use sdl2::rect::Rect;
 use std::rc::Rc;
-use std::time::Instant;
 
 pub trait System {
     fn execute(&mut self, delta_time: f32);
 }
 
-pub struct SysBallMovement {
+pub struct SysPhysics {
     cmanager: CManagerRc,
-    canvas: CanvasRc
+    grid: SpatialGrid,
+    // Scratch space reused by every step_body call
+    candidates: Vec<usize>,
+    others: Vec<Body>,
+    touched: Vec<Entity>
 }
 
-pub struct SysPaddleMovement {
+// Snapshot of a collider that the physics step sweeps movers against
+#[derive(Clone, Copy)]
+pub struct Body {
+    entity: Entity,
+    pos: Vec2,
+    size: Vec2,
+    shape: RoundedBox,
+    layer: u32
+}
+
+pub struct SysPaddleBounce {
+    cmanager: CManagerRc
+}
+
+pub struct SysPaddleSteering {
     cmanager: CManagerRc,
-    event_runner: EventRc,
-    canvas: CanvasRc
+    event_runner: EventRc
+}
+
+// Steers AI paddles once per tick, so they play the same at any frame rate
+pub struct SysPaddleAi {
+    cmanager: CManagerRc
+}
+
+pub struct SysPaddleMovement {
+    cmanager: CManagerRc
+}
+
+// Records where everything was before a tick moves it, for interpolation
+pub struct SysSnapshotPositions {
+    cmanager: CManagerRc
 }
 
 pub struct SysRenderTexture {
@@ -38,16 +75,39 @@ pub struct SysRenderText<'ttf> {
 
 pub struct SysButtonInput {
     cmanager: CManagerRc,
-    event_runner: EventRc,
-    canvas: CanvasRc,
+    event_runner: EventRc
+}
+
+// Runs the callback of every button clicked since its last run, however it was clicked
+pub struct SysButtonCallback {
+    cmanager: CManagerRc
+}
+
+pub struct SysBallReset {
+    cmanager: CManagerRc
+}
+
+pub struct SysBallSplit {
+    cmanager: CManagerRc
+}
+
+pub struct SysScoring {
+    cmanager: CManagerRc
+}
+
+pub struct SysMatchRules {
+    cmanager: CManagerRc
 }
 
-pub struct SysScoring<'ttf> {
+pub struct SysScoreDisplay<'ttf> {
     cmanager: CManagerRc,
     canvas: CanvasRc,
-    font_manager: FontRc<'ttf>,
-    score_p1: u8,
-    score_p2: u8
+    font_manager: FontRc<'ttf>
+}
+
+pub struct SysWindowSize {
+    cmanager: CManagerRc,
+    canvas: CanvasRc
 }
 
 impl<'ttf> SysRenderText<'ttf> {
@@ -59,187 +119,323 @@ impl<'ttf> SysRenderText<'ttf> {
         }
     }
 
-    pub fn render(&self, id: &usize) {
-        let cmanager = &*self.cmanager.borrow();
-        if let Some(text) = cmanager.ctext.get(id) {
-            if let Some(position) = cmanager.cposition_2d.get(id) {
-                self.font_manager.borrow_mut().render_text(&text.text, position.pos, "arial", text.size as u16, &self.canvas, Color::BLACK);
-            }
-        }
+    pub fn render(&self, text: &CText, position: &CPosition2D) {
+        self.font_manager.borrow_mut().render_text(&text.text, util::to_point(position.pos), "arial", text.size as u16, &self.canvas, Color::BLACK);
     }
 }
 
-impl SysBallMovement {
-    pub fn new(cmanager: &CManagerRc, canvas: &CanvasRc) -> Self {
+impl SysPhysics {
+    // Gap left after a contact so float error can't register it again next step
+    const CONTACT_SKIN: f32 = 0.01;
+
+    // Roughly a paddle's length, most bodies then only cover a cell or two
+    const CELL_SIZE: f32 = 64.0;
+
+    pub fn new(cmanager: &CManagerRc) -> Self {
         Self {
             cmanager: Rc::clone(cmanager),
-            canvas: Rc::clone(canvas)
+            grid: SpatialGrid::new(Self::CELL_SIZE),
+            candidates: Vec::new(),
+            others: Vec::new(),
+            touched: Vec::new()
         }
     }
 
-    pub fn move_ball(&self, ball_id: &usize, delta_time: f32) {
-        let cmanager = &mut *self.cmanager.borrow_mut();
-        let position = cmanager.cposition_2d.get_mut(ball_id);
-        let movement = cmanager.cmovement_2d.get_mut(ball_id);
-
-        if matches!(position, None) || matches!(movement, None) {
+    // Mirrors the movement off a surface, unless it is already heading away
+    pub fn reflect(movement: &mut CMovement2D, normal: Vec2) {
+        let velocity = Vec2::new(movement.x, movement.y);
+        let along = velocity.dot(normal);
+        if along >= 0.0 {
             return;
         }
 
-        let position = position.unwrap();
-        let movement = movement.unwrap();
-        
-        let x_offset = (movement.x * movement.speed * delta_time) as i32;
-        let y_offset = (movement.y * movement.speed * delta_time) as i32;
-        position.pos = position.pos.offset(x_offset, y_offset);
+        let reflected = velocity - normal * (2.0 * along);
+        movement.x = reflected.x;
+        movement.y = reflected.y;
+    }
+
+    /*
+        Moves one dynamic body through its step. Anything that moved into it
+        since the last tick pushes it out first, then it is swept against every
+        body its mask accepts. Each contact spends part of the step, reflects
+        the body and the rest of the step carries on in the new direction, so
+        fast bodies can't skip over thin ones between two ticks.
 
-        let collision = cmanager.ccollision_2d.get_mut(ball_id).unwrap();
-        let (_, height) = self.canvas.borrow().window().size();
+        Only bodies the grid files near the step's reach are considered.
+        Index is where the body sits in bodies, None for movers without a
+        collider.
+    */
+    pub fn step_body(
+            &mut self,
+            cmanager: &ComponentManager,
+            entity: Entity,
+            index: Option<usize>,
+            bodies: &mut [Body],
+            delta_time: f32) {
+        let mut query = cmanager.query::<(&mut CPosition2D, &mut CMovement2D, Option<&CCollision2D>)>();
+        let Some((position, movement, collision)) = query.get(&entity) else {
+            return;
+        };
 
-        let ball_collision_rect = Rect::from_center(position.pos, collision.size.0, collision.size.1);
+        let Some(collision) = collision else {
+            position.pos += Vec2::new(movement.x, movement.y) * (movement.speed * delta_time);
+            return;
+        };
 
-        if ball_collision_rect.top() < 0 {
-            position.pos = Point::new(position.pos.x, (collision.size.1 / 2) as i32);
-            movement.y *= -1.0;
-        }
-        else if ball_collision_rect.bottom() > height as i32 {
-            position.pos = Point::new(position.pos.x, (height - (collision.size.1 / 2)) as i32);
-            movement.y *= -1.0;
+        // However it gets reflected, the body can't end up further away than a full step
+        let step_length = movement.speed * delta_time * Vec2::new(movement.x, movement.y).length();
+        let reach = collision.size + Vec2::new(step_length, step_length) * 2.0;
+        self.grid.query(position.pos, reach, &mut self.candidates);
+
+        let others = &mut self.others;
+        others.clear();
+        others.extend(self.candidates.iter()
+            .map(|&candidate| bodies[candidate])
+            .filter(|body| body.entity != entity && collision.mask & body.layer != 0));
+
+        let shape = collision.rounded_box();
+        let touched = &mut self.touched;
+        touched.clear();
+
+        for other in others.iter() {
+            let Some(push) = collision::penetration(position.pos, shape, other.pos, other.shape) else {
+                continue;
+            };
+
+            position.pos += push;
+
+            let normal = push.normalized();
+
+            if Vec2::new(movement.x, movement.y).dot(normal) < 0.0 {
+                Self::reflect(movement, normal);
+                cmanager.send_event(Contact { entity, other: other.entity, normal, offset: position.pos - other.pos });
+            }
+
+            touched.push(other.entity);
         }
 
-        let ball_collision_rect = Rect::from_center(position.pos, collision.size.0, collision.size.1);
-        
-        let mut paddle_ids = Vec::new();
-        
-        for key in cmanager.cpaddle_info.keys() {
-            paddle_ids.push(*key);
+        let mut remaining = 1.0;
+        let mut motion = Vec2::new(movement.x, movement.y) * (movement.speed * delta_time);
+
+        loop {
+            let earliest = others.iter()
+                .filter(|other| !touched.contains(&other.entity))
+                .filter_map(|other| {
+                    collision::sweep(position.pos, shape, motion, other.pos, other.shape)
+                        .map(|hit| (other, hit))
+                })
+                .min_by(|(_, a), (_, b)| a.time.total_cmp(&b.time));
+
+            let Some((other, hit)) = earliest else {
+                break;
+            };
+
+            position.pos += motion * hit.time + hit.normal * Self::CONTACT_SKIN;
+            remaining *= 1.0 - hit.time;
+
+            Self::reflect(movement, hit.normal);
+            motion = Vec2::new(movement.x, movement.y) * (movement.speed * delta_time * remaining);
+
+            touched.push(other.entity);
+            cmanager.send_event(Contact { entity, other: other.entity, normal: hit.normal, offset: position.pos - other.pos });
         }
 
-        for paddle_id in paddle_ids.iter() {
-            let collision = cmanager.ccollision_2d.get_mut(paddle_id).unwrap();
-            let position = cmanager.cposition_2d.get_mut(paddle_id).unwrap();
+        position.pos += motion;
 
-            let paddle_collision_rect = Rect::from_center(position.pos, collision.size.0, collision.size.1);
-            let intersects = paddle_collision_rect.has_intersection(ball_collision_rect);
-            if intersects {
-                movement.x *= -1.0;
-            }
+        // Later bodies this tick collide against where this one ended up
+        if let Some(index) = index {
+            self.grid.remove(index, bodies[index].pos, bodies[index].size);
+            bodies[index].pos = position.pos;
+            self.grid.insert(index, bodies[index].pos, bodies[index].size);
         }
     }
 }
 
-impl SysButtonInput {
-    pub fn new(cmanager: &CManagerRc, event_runner: &EventRc, canvas: &CanvasRc) -> Self {
+impl SysPaddleBounce {
+    pub fn new(cmanager: &CManagerRc) -> Self {
         Self {
-            cmanager: Rc::clone(cmanager),
-            event_runner: Rc::clone(event_runner),
-            canvas: Rc::clone(canvas),
+            cmanager: Rc::clone(cmanager)
         }
     }
 
-    pub fn handle_input(&self, id: &usize) {
-        let cmanager = &*self.cmanager.borrow();
+    /*
+        Physics has already reflected the ball off the paddle. Hits that are
+        mostly on the paddle's face are then re-aimed by how far from its
+        centre the ball struck and sped up; hits on a paddle's end keep the
+        plain reflection.
+    */
+    pub fn bounce(&self, contact: &Contact) {
+        let cmanager = self.cmanager.borrow();
+        let on_face = contact.normal.x.abs() > contact.normal.y.abs();
+        if !on_face || !cmanager.has::<CBall>(&contact.entity) || !cmanager.has::<CPaddleInfo>(&contact.other) {
+            return;
+        }
+
+        let Some(paddle_size) = cmanager.get::<CCollision2D>(&contact.other).map(|collision| collision.size) else {
+            return;
+        };
+        let config = cmanager.resource::<MatchConfig>().map(|config| config.clone()).unwrap_or_default();
 
-        if matches!(cmanager.cbutton_info.get(id), None) {
+        let mut query = cmanager.query::<(&mut CMovement2D, &CCollision2D)>();
+        let Some((movement, collision)) = query.get(&contact.entity) else {
             return;
+        };
+
+        // Directions aren't unit length, keep the ball's overall pace
+        let reach = (collision.size + paddle_size) * 0.5;
+        let length = Vec2::new(movement.x, movement.y).length();
+        let angle = (contact.offset.y / reach.y).clamp(-1.0, 1.0) * config.max_bounce_angle;
+
+        movement.x = contact.normal.x.signum() * angle.cos() * length;
+        movement.y = angle.sin() * length;
+        movement.speed = (movement.speed + config.ball_speed_increase).min(config.max_ball_speed);
+
+        cmanager.send_event(PaddleHit { ball: contact.entity });
+    }
+}
+
+impl SysButtonInput {
+    pub fn new(cmanager: &CManagerRc, event_runner: &EventRc) -> Self {
+        Self {
+            cmanager: Rc::clone(cmanager),
+            event_runner: Rc::clone(event_runner)
         }
+    }
 
-        let button_info = cmanager.cbutton_info.get(id).unwrap();
-        let pos = cmanager.cposition_2d.get(id).unwrap();
-        let collision = cmanager.ccollision_2d.get(id).unwrap();
+    pub fn handle_input(&self, entity: Entity, pos: &CPosition2D, collision: &CCollision2D) -> bool {
+        let mut clicked = false;
 
         for event in &self.event_runner.borrow().event_list {
-            match event {
-                Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } => {
-                    let button_rect = Rect::from_center(pos.pos, collision.size.0, collision.size.1);
-                    if button_rect.contains_point(Point::new(*x, *y)) {
-                        println!("You clicked at {}-{}", x, y);
-                        let callback = button_info.callback.as_ref().unwrap();
-                        callback();
-                    }
-                },
-                _ => {}
+            if let Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } = event {
+                let button_rect = util::centered_rect(pos.pos, collision.size);
+                if button_rect.contains_point(Point::new(*x, *y)) {
+                    println!("You clicked {:?} at {}-{}", entity, x, y);
+                    clicked = true;
+                }
             }
         }
+
+        clicked
     }
 }
 
-impl SysPaddleMovement {
-    pub fn new(cmanager: &CManagerRc, event_runner: &EventRc, canvas: &CanvasRc) -> Self {
+impl SysButtonCallback {
+    pub fn new(cmanager: &CManagerRc) -> Self {
+        Self {
+            cmanager: Rc::clone(cmanager)
+        }
+    }
+}
+
+impl SysPaddleSteering {
+    pub fn new(cmanager: &CManagerRc, event_runner: &EventRc) -> Self {
         Self {
             cmanager: Rc::clone(cmanager),
-            event_runner: Rc::clone(event_runner),
-            canvas: Rc::clone(canvas)
+            event_runner: Rc::clone(event_runner)
         }
     }
 
-    pub fn move_paddle(&self, id: &usize, delta_time: f32) {
-        let ball_id = ball::get_ball_id(&self.cmanager);
+    pub fn steer_paddle(&self, paddle_info: &mut CPaddleInfo, binding: &CInputBinding) {
+        let event_runner = self.event_runner.borrow();
 
-        let cmanager = &mut *self.cmanager.borrow_mut();
-        let paddle_info = cmanager.cpaddle_info.get_mut(id).unwrap();
+        // Holding both ways moves the way pressed last, letting go of it hands back to the other
+        let held = event_runner.actions.latest_held(&[binding.up, binding.down]);
 
-        if paddle_info.is_ai && paddle_info.is_delay_done() && !matches!(ball_id, None) {
-            let ball_pos_y = cmanager.cposition_2d.get_mut(&ball_id.unwrap()).unwrap().pos.y;
-            let paddle_pos = cmanager.cposition_2d.get_mut(id).unwrap();
-            let direction = ball_pos_y - paddle_pos.pos.y;
-            if direction < 0 {
-                paddle_info.direction = Direction::Up;
-            }
-            else if direction > 1 {
-                paddle_info.direction = Direction::Down;
-            }
-            else {
-                paddle_info.direction = Direction::Stationary;
+        // Keys win over the mouse or controller, which steer while none are held
+        paddle_info.direction = match held {
+            Some(action) if action == binding.up => Direction::Up,
+            Some(_) => Direction::Down,
+            None if binding.follows_mouse => match event_runner.held.mouse_position() {
+                Some((_, y)) => Direction::Follow(y as f32),
+                None => Direction::Stationary
+            },
+            None => match event_runner.controllers.steering(binding.side) {
+                Some(amount) => Direction::Analog(amount),
+                None => Direction::Stationary
             }
+        };
+    }
+}
+
+impl SysPaddleAi {
+    pub fn new(cmanager: &CManagerRc) -> Self {
+        Self {
+            cmanager: Rc::clone(cmanager)
+        }
+    }
+
+    // The closest ball heading for the paddle, or just the closest if none are
+    pub fn target_ball(balls: &[(Vec2, f32)], paddle_pos: Vec2) -> Option<Vec2> {
+        let distance = |ball_pos: &Vec2| (paddle_pos.x - ball_pos.x).abs();
+        let incoming = balls.iter()
+            .filter(|(ball_pos, movement_x)| (paddle_pos.x - ball_pos.x) * movement_x > 0.0)
+            .map(|(ball_pos, _)| *ball_pos)
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)));
+
+        incoming.or_else(|| {
+            balls.iter()
+                .map(|(ball_pos, _)| *ball_pos)
+                .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+        })
+    }
+
+    // Heads for the ball, only changing its mind once its delay has run out
+    pub fn steer_paddle(paddle_info: &mut CPaddleInfo, position: &CPosition2D, ball_pos_y: Option<f32>, delta_time: f32) {
+        if !paddle_info.tick_ai_delay(delta_time) {
+            return;
+        }
+
+        let Some(ball_pos_y) = ball_pos_y else {
+            return;
+        };
 
-            paddle_info.ai_delay_timer = Instant::now();
+        let direction = ball_pos_y - position.pos.y;
+        if direction < 0.0 {
+            paddle_info.direction = Direction::Up;
+        }
+        else if direction > 1.0 {
+            paddle_info.direction = Direction::Down;
         }
         else {
-            for event in &self.event_runner.borrow().event_list {
-                match event {
-                    Event::Quit {..} |
-                    Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
-                        //*play = false;
-                    },
-                    Event::KeyDown { keycode: Some(Keycode::W), .. } => {
-                        paddle_info.direction = Direction::Up;
-                    },
-                    Event::KeyUp { keycode: Some(Keycode::W), .. } => {
-                        paddle_info.direction = Direction::Stationary;
-                    },
-                    Event::KeyDown { keycode: Some(Keycode::S), .. } => {
-                        paddle_info.direction = Direction::Down;
-                    },
-                    Event::KeyUp { keycode: Some(Keycode::S), .. } => {
-                        paddle_info.direction = Direction::Stationary;
-                    },
-                    _ => {}
-                }
-            }
+            paddle_info.direction = Direction::Stationary;
         }
+    }
+}
 
-        let position = cmanager.cposition_2d.get_mut(id).unwrap();
-        let movement = cmanager.cmovement_2d.get_mut(id).unwrap();
-        let paddle_info = cmanager.cpaddle_info.get_mut(id).unwrap();
+impl SysPaddleMovement {
+    pub fn new(cmanager: &CManagerRc) -> Self {
+        Self {
+            cmanager: Rc::clone(cmanager)
+        }
+    }
 
-        
+    pub fn move_paddle(
+            &self,
+            paddle_info: &CPaddleInfo,
+            position: &mut CPosition2D,
+            movement: &CMovement2D,
+            collision: &CCollision2D,
+            height: u32,
+            delta_time: f32) {
         match paddle_info.direction {
-            Direction::Up => position.pos = position.pos.offset(0, (-movement.speed * delta_time) as i32),
-            Direction::Down => position.pos = position.pos.offset(0, (movement.speed * delta_time) as i32),
+            Direction::Up => position.pos.y -= movement.speed * delta_time,
+            Direction::Down => position.pos.y += movement.speed * delta_time,
+            Direction::Analog(amount) => position.pos.y += movement.speed * amount * delta_time,
+            // Never faster than a key would move it, so the mouse can't outrun the keyboard
+            Direction::Follow(target) => {
+                let reach = movement.speed * delta_time;
+                position.pos.y += (target - position.pos.y).clamp(-reach, reach);
+            },
             _ => ()
         }
 
-        let collision = cmanager.ccollision_2d.get_mut(id).unwrap();
-        let (_, height) = self.canvas.borrow().window().size();
-
-        let collision_rect = Rect::from_center(position.pos, collision.size.0, collision.size.1);
+        let half_size = collision.half_size();
 
-        if collision_rect.top() < 0 {
-            position.pos = Point::new(position.pos.x, (collision.size.1 / 2) as i32);
+        if position.pos.y - half_size.y < 0.0 {
+            position.pos.y = half_size.y;
         }
-        else if collision_rect.bottom() > height as i32 {
-            position.pos = Point::new(position.pos.x, (height - (collision.size.1 / 2)) as i32);
+        else if position.pos.y + half_size.y > height as f32 {
+            position.pos.y = height as f32 - half_size.y;
         }
     }
 }
@@ -252,131 +448,811 @@ impl SysRenderTexture {
         }
     }
 
-    pub fn render(&self, id: &usize) {
-        let cmanager = &*self.cmanager.borrow();
-        if let Some(texture) = cmanager.ctexture.get(id) {
-            if let Some(position) = cmanager.cposition_2d.get(id) {
-                let draw_area = Rect::from_center(position.pos, texture.size.0, texture.size.1);
+    pub fn render(&self, texture: &CTexture, position: &CPosition2D, alpha: f32) {
+        let center = util::to_point(position.interpolated(alpha));
+        let mut canvas = self.canvas.borrow_mut();
 
-                self.canvas.borrow_mut().set_draw_color(texture.color);
-                self.canvas.borrow_mut().fill_rect(draw_area).unwrap();
-            }
+        canvas.set_draw_color(texture.color);
+
+        if texture.shape == Shape::Aabb {
+            canvas.fill_rect(Rect::from_center(center, texture.size.0, texture.size.1)).unwrap();
+            return;
+        }
+
+        // Rounded shapes are filled one pixel row at a time
+        let size = Vec2::new(texture.size.0 as f32, texture.size.1 as f32);
+        let shape = RoundedBox::new(texture.shape, size);
+        let half_height = (size.y / 2.0).round() as i32;
+
+        let rows: Vec<Rect> = (-half_height..half_height)
+            .filter_map(|row| {
+                // How far the row reaches into a rounded end, zero along straight sides
+                let into_end = ((row as f32 + 0.5).abs() - shape.half.y).max(0.0);
+                if into_end > shape.radius {
+                    return None;
+                }
+
+                let half_width = (shape.half.x + (shape.radius * shape.radius - into_end * into_end).sqrt()).round() as i32;
+                Some(Rect::new(center.x - half_width, center.y + row, (half_width * 2).max(1) as u32, 1))
+            })
+            .collect();
+
+        canvas.fill_rects(&rows).unwrap();
+    }
+}
+
+impl SysScoring {
+    pub fn new(cmanager: &CManagerRc) -> Self {
+        Self {
+            cmanager: Rc::clone(cmanager)
+        }
+    }
+
+    pub fn check_score(&mut self, ball_id: &Entity, _delta_time: f32) {
+        let cmanager = self.cmanager.borrow();
+        let Some(width) = cmanager.resource::<WindowSize>().map(|window| window.width) else {
+            return;
+        };
+        let Some(mut score) = cmanager.resource_mut::<Score>() else {
+            return;
+        };
+
+        // The ball stays out of bounds until SysBallReset picks up the goal,
+        // so only count it once
+        let already_scored = cmanager.events::<GoalScored>()
+            .is_some_and(|goals| goals.iter().any(|goal| goal.ball == *ball_id));
+
+        let ball_x = cmanager.get::<CPosition2D>(ball_id).map(|position| position.pos.x);
+        let Some(ball_x) = ball_x.filter(|_| !already_scored) else {
+            return;
+        };
+
+        let goal = if ball_x < 0.0 {
+            GoalScored { side: Side::Right, ball: *ball_id }
         }
+        else if ball_x > width as f32 {
+            GoalScored { side: Side::Left, ball: *ball_id }
+        }
+        else {
+            return;
+        };
+
+        score.award(goal.side);
+        cmanager.send_event(goal);
     }
 }
 
-impl<'ttf> SysScoring<'ttf> {
+impl<'ttf> SysScoreDisplay<'ttf> {
     pub fn new(cmanager: &CManagerRc, canvas: &CanvasRc, font_manager: &FontRc<'ttf>) -> Self {
         Self {
             cmanager: Rc::clone(cmanager),
             canvas: Rc::clone(canvas),
-            font_manager: Rc::clone(font_manager),
-            score_p1: 0,
-            score_p2: 0,
+            font_manager: Rc::clone(font_manager)
         }
     }
 
-    pub fn check_score(&mut self, ball_id: &usize, _delta_time: f32) {
-        let (width, _) = self.canvas.borrow().window().size();
+    pub fn render(&self) {
+        let cmanager = self.cmanager.borrow();
+        let Some(score) = cmanager.resource::<Score>().map(|score| *score) else {
+            return;
+        };
+        let time_limit = cmanager.resource::<MatchConfig>().and_then(|config| config.time_limit);
+        let elapsed = cmanager.resource::<MatchState>().map_or(0.0, |state| state.elapsed);
+
+        let mut font_manager = self.font_manager.borrow_mut();
+        let font = "arial";
+
+        let pos_p1 = Point::new(540, 100);
+        let pos_p2 = Point::new(740, 100);
 
-        let cmanager = &mut *self.cmanager.borrow_mut();
-        let ball_position = cmanager.cposition_2d.get_mut(ball_id).unwrap();
+        font_manager.render_text(&score.p1.to_string(), pos_p1, font, 144, &self.canvas, Color::WHITE);
+        font_manager.render_text(&score.p2.to_string(), pos_p2, font, 144, &self.canvas, Color::WHITE);
 
-        let mut has_scored = false;
+        if let Some(limit) = time_limit {
+            let clock = if elapsed >= limit {
+                "Sudden Death".to_string()
+            }
+            else {
+                let seconds_left = (limit - elapsed).ceil() as u32;
+                format!("{}:{:02}", seconds_left / 60, seconds_left % 60)
+            };
 
-        if ball_position.pos.x < 0 {
-            ball_position.pos = Point::new(ball::START_BALL.0, ball::START_BALL.1);
-            self.score_p2 += 1;
-            has_scored = true;
+            font_manager.render_text(&clock, Point::new(640, 30), font, 48, &self.canvas, Color::WHITE);
         }
-        else if ball_position.pos.x > width as i32 {
-            ball_position.pos = Point::new(ball::START_BALL.0, ball::START_BALL.1);
-            self.score_p1 += 1;
-            has_scored = true;
+    }
+}
+
+impl SysMatchRules {
+    pub fn new(cmanager: &CManagerRc) -> Self {
+        Self {
+            cmanager: Rc::clone(cmanager)
+        }
+    }
+}
+
+impl SysBallReset {
+    pub fn new(cmanager: &CManagerRc) -> Self {
+        Self {
+            cmanager: Rc::clone(cmanager)
         }
+    }
+
+    pub fn reset_ball(&self, ball_id: &Entity) {
+        let cmanager = self.cmanager.borrow();
+        let ball_speed = cmanager.resource::<MatchConfig>().map_or(MatchConfig::default().ball_speed, |config| config.ball_speed);
+        let mut query = cmanager.query::<(&mut CPosition2D, &mut CMovement2D, Option<&mut CBall>)>();
+
+        if let Some((ball_position, ball_movement, ball)) = query.get(ball_id) {
+            ball_position.teleport(ball::START_BALL);
 
-        if has_scored {
-            let ball_movement = cmanager.cmovement_2d.get_mut(ball_id).unwrap();
-            let (x, y) = util::random_direction();
+            let (x, y) = util::world_random_direction(&cmanager);
             ball_movement.x = x;
             ball_movement.y = y;
+            // Rally speed-ups don't carry over into the next serve
+            ball_movement.speed = ball_speed;
+            // Nor does its count towards the next split
+            if let Some(ball) = ball {
+                ball.hits = 0;
+            }
         }
+    }
+}
 
-        let mut font_manager = self.font_manager.borrow_mut();
-        let font = "arial";
+impl SysBallSplit {
+    // Flattest a split may leave at relative to its parent, so the two never share a path
+    const MIN_SPLIT_ANGLE: f32 = std::f32::consts::PI / 12.0;
 
-        if self.score_p1 >= 5 {
-            font_manager.render_text("Player 1 Wins", Point::new(640, 360), font, 144, &self.canvas, Color::WHITE);
+    pub fn new(cmanager: &CManagerRc) -> Self {
+        Self {
+            cmanager: Rc::clone(cmanager)
         }
-        else if self.score_p2 >= 5 {
-            font_manager.render_text("Player 2 Wins", Point::new(640, 360), font, 144, &self.canvas, Color::WHITE);
+    }
+
+    /*
+        Counts the hit against the ball and, every split_every hits, splits
+        it in two. The new ball leaves from the same spot mirrored vertically,
+        or angled off if the parent is flying too flat for a mirror to part
+        them, and joins the world through commands at the next sync point.
+    */
+    pub fn split_ball(&self, ball_id: &Entity, config: &MatchConfig, ball_count: &mut usize) {
+        let cmanager = self.cmanager.borrow();
+        let mut query = cmanager.query::<(&mut CBall, &CPosition2D, &CMovement2D)>();
+        let Some((ball, position, movement)) = query.get(ball_id) else {
+            return;
+        };
+
+        ball.hits += 1;
+
+        let due = config.split_every > 0 && ball.hits % config.split_every == 0;
+        if !due || *ball_count >= config.max_balls as usize {
+            return;
         }
-        else {
-            let pos_p1 = Point::new(540, 100);
-            let pos_p2 = Point::new(740, 100);
-    
-            font_manager.render_text(&self.score_p1.to_string(), pos_p1, font, 144, &self.canvas, Color::WHITE);
-            font_manager.render_text(&self.score_p2.to_string(), pos_p2, font, 144, &self.canvas, Color::WHITE);
+
+        let (pos, direction, speed) = (position.pos, Self::split_direction(movement), movement.speed);
+        cmanager.commands().spawn(move |cmanager, entity| ball::spawn(cmanager, entity, pos, direction, speed));
+        *ball_count += 1;
+    }
+
+    // The parent's direction mirrored vertically, at least MIN_SPLIT_ANGLE off horizontal
+    pub fn split_direction(movement: &CMovement2D) -> (f32, f32) {
+        let length = Vec2::new(movement.x, movement.y).length();
+        let angle = (movement.y / length).clamp(-1.0, 1.0).asin();
+        let mirrored = match angle.abs() < Self::MIN_SPLIT_ANGLE {
+            true => -angle.signum() * Self::MIN_SPLIT_ANGLE,
+            false => -angle
+        };
+
+        (movement.x.signum() * mirrored.cos() * length, mirrored.sin() * length)
+    }
+}
+
+impl SysSnapshotPositions {
+    pub fn new(cmanager: &CManagerRc) -> Self {
+        Self {
+            cmanager: Rc::clone(cmanager)
         }
     }
 }
 
-impl System for SysBallMovement {
+impl SysWindowSize {
+    pub fn new(cmanager: &CManagerRc, canvas: &CanvasRc) -> Self {
+        Self {
+            cmanager: Rc::clone(cmanager),
+            canvas: Rc::clone(canvas)
+        }
+    }
+}
+
+impl System for SysPhysics {
     fn execute(&mut self, delta_time: f32) {
-        if let Some(ball_id) = ball::get_ball_id(&self.cmanager) {
-            self.move_ball(&ball_id, delta_time);
+        let cmanager_rc = Rc::clone(&self.cmanager);
+        let cmanager = cmanager_rc.borrow();
+
+        // Movers keep the index of their body so it can be refiled after moving
+        let mut bodies = Vec::new();
+        let mut movers = Vec::new();
+        cmanager.query::<(&CPosition2D, &CCollision2D, Option<&CMovement2D>)>()
+            .for_each(|entity, (position, collision, movement)| {
+                if movement.is_some() && !collision.is_static {
+                    movers.push((entity, Some(bodies.len())));
+                }
+
+                bodies.push(Body {
+                    entity,
+                    pos: position.pos,
+                    size: collision.size,
+                    shape: collision.rounded_box(),
+                    layer: collision.layer
+                });
+            });
+
+        cmanager.query::<(&CMovement2D, Without<CCollision2D>)>().for_each(|entity, _| {
+            movers.push((entity, None));
+        });
+
+        self.grid.clear();
+        for (index, body) in bodies.iter().enumerate() {
+            self.grid.insert(index, body.pos, body.size);
         }
+
+        for (entity, index) in movers {
+            self.step_body(&cmanager, entity, index, &mut bodies, delta_time);
+        }
+    }
+}
+
+impl System for SysPaddleBounce {
+    fn execute(&mut self, _delta_time: f32) {
+        let contacts = self.cmanager.borrow().read_events::<Contact>();
+
+        for contact in contacts.iter() {
+            self.bounce(contact);
+        }
+    }
+}
+
+impl System for SysPaddleSteering {
+    fn execute(&mut self, _delta_time: f32) {
+        let cmanager = self.cmanager.borrow();
+
+        // Only human paddles are bound to input, the AI steers in the simulation
+        cmanager.query::<(&mut CPaddleInfo, &CInputBinding)>().for_each(|_, (paddle_info, binding)| {
+            self.steer_paddle(paddle_info, binding);
+        });
     }
-} 
+}
+
+impl System for SysPaddleAi {
+    fn execute(&mut self, delta_time: f32) {
+        let cmanager = self.cmanager.borrow();
+
+        let mut balls = Vec::new();
+        cmanager.query::<(&CPosition2D, &CMovement2D, With<CBall>)>().for_each(|_, (position, movement, _)| {
+            balls.push((position.pos, movement.x));
+        });
+
+        cmanager.query::<(&mut CPaddleInfo, &CPosition2D)>().for_each(|_, (paddle_info, position)| {
+            if paddle_info.is_ai {
+                let ball_pos_y = Self::target_ball(&balls, position.pos).map(|ball_pos| ball_pos.y);
+                Self::steer_paddle(paddle_info, position, ball_pos_y, delta_time);
+            }
+        });
+    }
+}
 
 impl System for SysPaddleMovement {
     fn execute(&mut self, delta_time: f32) {
-        let mut ids = Vec::new();
-        
-        for key in self.cmanager.borrow().cpaddle_info.keys() {
-            ids.push(*key);
+        let cmanager = self.cmanager.borrow();
+        let Some(height) = cmanager.resource::<WindowSize>().map(|window| window.height) else {
+            return;
+        };
+
+        cmanager.query::<(&CPaddleInfo, &mut CPosition2D, &CMovement2D, &CCollision2D)>()
+            .for_each(|_, (paddle_info, position, movement, collision)| {
+                self.move_paddle(paddle_info, position, movement, collision, height, delta_time);
+            });
+    }
+}
+
+impl System for SysSnapshotPositions {
+    fn execute(&mut self, _delta_time: f32) {
+        let cmanager = self.cmanager.borrow();
+
+        cmanager.query::<&mut CPosition2D>().for_each(|_, position| {
+            position.prev = position.pos;
+        });
+    }
+}
+
+impl System for SysRenderTexture {
+    fn execute(&mut self, _delta_time: f32) {
+        let cmanager = self.cmanager.borrow();
+        let alpha = cmanager.resource::<Interpolation>().map_or(1.0, |interpolation| interpolation.alpha);
+
+        cmanager.query::<(&CTexture, &CPosition2D)>().for_each(|_, (texture, position)| {
+            self.render(texture, position, alpha);
+        });
+    }
+}
+
+impl<'tff> System for SysRenderText<'tff> {
+    fn execute(&mut self, _delta_time: f32) {
+        let cmanager = self.cmanager.borrow();
+
+        cmanager.query::<(&CText, &CPosition2D)>().for_each(|_, (text, position)| {
+            self.render(text, position);
+        });
+    }
+}
+
+impl System for SysScoring {
+    fn execute(&mut self, delta_time: f32) {
+        let balls = self.cmanager.borrow().query::<With<CBall>>().entities();
+
+        for ball_id in balls.iter() {
+            self.check_score(ball_id, delta_time);
         }
+    }
+}
 
-        for id in &ids[..] {
-            self.move_paddle(id, delta_time);
+impl System for SysButtonInput {
+    fn execute(&mut self, _delta_time: f32) {
+        let cmanager = self.cmanager.borrow();
+
+        cmanager.query::<(&CPosition2D, &CCollision2D, With<CButtonInfo>)>().for_each(|entity, (pos, collision, _)| {
+            if self.handle_input(entity, pos, collision) {
+                cmanager.send_event(ButtonClicked { entity });
+            }
+        });
+    }
+}
+
+impl System for SysButtonCallback {
+    fn execute(&mut self, _delta_time: f32) {
+        let cmanager = self.cmanager.borrow();
+
+        for clicked in cmanager.read_events::<ButtonClicked>() {
+            let button_info = cmanager.get::<CButtonInfo>(&clicked.entity);
+            if let Some(callback) = button_info.as_ref().and_then(|button_info| button_info.callback.as_ref()) {
+                callback();
+            }
         }
     }
 }
 
-impl System for SysRenderTexture {
+impl System for SysWindowSize {
     fn execute(&mut self, _delta_time: f32) {
-        let ids = util::get_allocated_ids(&self.cmanager);
+        let (width, height) = self.canvas.borrow().window().size();
+        let window_size = WindowSize { width, height };
 
-        for id in &ids[..] {
-            self.render(id);
+        let mut cmanager = self.cmanager.borrow_mut();
+        if let Some(mut resource) = cmanager.resource_mut::<WindowSize>() {
+            *resource = window_size;
+            return;
         }
+        cmanager.insert_resource(window_size);
     }
 }
 
-impl<'tff> System for SysRenderText<'tff> {
+impl System for SysBallReset {
     fn execute(&mut self, _delta_time: f32) {
-        let ids = util::get_allocated_ids(&self.cmanager);
+        let goals = self.cmanager.borrow().read_events::<GoalScored>();
+        if goals.is_empty() {
+            return;
+        }
+
+        let (mut balls_left, ball_count) = {
+            let cmanager = self.cmanager.borrow();
+            let ball_count = cmanager.resource::<MatchConfig>().map_or(1, |config| config.ball_count);
+            let balls_left = cmanager.components::<CBall>().map_or(0, |balls| balls.len());
+            (balls_left, ball_count as usize)
+        };
 
-        for id in &ids[..] {
-            self.render(id);
+        for goal in goals.iter() {
+            // Balls split off during a rally go out of play rather than being served again
+            if balls_left > ball_count {
+                self.cmanager.borrow().commands().despawn(goal.ball);
+                balls_left -= 1;
+            }
+            else {
+                self.reset_ball(&goal.ball);
+            }
         }
     }
 }
 
-impl<'ttf> System for SysScoring<'ttf> {
+impl System for SysBallSplit {
+    fn execute(&mut self, _delta_time: f32) {
+        let hits = self.cmanager.borrow().read_events::<PaddleHit>();
+        if hits.is_empty() {
+            return;
+        }
+
+        let (config, mut ball_count) = {
+            let cmanager = self.cmanager.borrow();
+            let config = cmanager.resource::<MatchConfig>().map(|config| config.clone()).unwrap_or_default();
+            let ball_count = cmanager.components::<CBall>().map_or(0, |balls| balls.len());
+            (config, ball_count)
+        };
+
+        for hit in hits.iter() {
+            self.split_ball(&hit.ball, &config, &mut ball_count);
+        }
+    }
+}
+
+// Runs the clock and settles the match once the rules say it is over
+impl System for SysMatchRules {
     fn execute(&mut self, delta_time: f32) {
-        if let Some(ball_id) = ball::get_ball_id(&self.cmanager) {
-            self.check_score(&ball_id, delta_time);
+        let cmanager = self.cmanager.borrow();
+        let Some(score) = cmanager.resource::<Score>().map(|score| *score) else {
+            return;
+        };
+        let Some(mut state) = cmanager.resource_mut::<MatchState>() else {
+            return;
+        };
+
+        if state.result.is_some() {
+            return;
         }
+
+        let config = cmanager.resource::<MatchConfig>().map(|config| config.clone()).unwrap_or_default();
+        state.elapsed += delta_time;
+        state.result = config.result(&score, state.elapsed);
     }
 }
 
-impl System for SysButtonInput {
+impl<'ttf> System for SysScoreDisplay<'ttf> {
     fn execute(&mut self, _delta_time: f32) {
-        let ids = util::get_allocated_ids(&self.cmanager);
+        self.render();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{LAYER_BALL, LAYER_NONE, LAYER_PADDLE, LAYER_WALL};
+    use crate::util;
 
-        for id in &ids[..] {
-            self.handle_input(id);
+    fn assert_near(actual: Vec2, expected: Vec2) {
+        assert!((actual - expected).length() < 0.001, "{:?} != {:?}", actual, expected);
+    }
+
+    fn world() -> CManagerRc {
+        let cmanager = util::create_component_manager();
+        cmanager.borrow_mut().add_event::<Contact>();
+        cmanager
+    }
+
+    fn body(cmanager: &CManagerRc, pos: Vec2, size: Vec2, shape: Shape, layer: u32, mask: u32, is_static: bool) -> Entity {
+        let mut cmanager = cmanager.borrow_mut();
+        let id = cmanager.create_entity();
+        cmanager.insert(id, CPosition2D::new(id, "body", pos));
+        cmanager.insert(id, CCollision2D { id, name: "body".to_string(), size, shape, layer, mask, is_static });
+        id
+    }
+
+    fn ball(cmanager: &CManagerRc, pos: Vec2, mask: u32, direction: Vec2, speed: f32) -> Entity {
+        let id = body(cmanager, pos, Vec2::new(10.0, 10.0), Shape::Circle, LAYER_BALL, mask, false);
+        cmanager.borrow_mut().insert(id, CMovement2D { id, name: "ball".to_string(), speed, x: direction.x, y: direction.y });
+        id
+    }
+
+    fn paddle_info(id: Entity) -> CPaddleInfo {
+        CPaddleInfo {
+            id,
+            name: "paddle".to_string(),
+            direction: Direction::Stationary,
+            ai_delay_elapsed: 0.0,
+            ai_delay: 0.25,
+            is_ai: true
         }
     }
-}
\ No newline at end of file
+
+    fn position(cmanager: &CManagerRc, entity: Entity) -> Vec2 {
+        cmanager.borrow().get::<CPosition2D>(&entity).unwrap().pos
+    }
+
+    fn direction(cmanager: &CManagerRc, entity: Entity) -> Vec2 {
+        let cmanager = cmanager.borrow();
+        let movement = cmanager.get::<CMovement2D>(&entity).unwrap();
+        Vec2::new(movement.x, movement.y)
+    }
+
+    fn contacts(cmanager: &CManagerRc) -> Vec<Contact> {
+        cmanager.borrow_mut().update_events();
+        cmanager.borrow().read_events::<Contact>()
+    }
+
+    #[test]
+    fn static_bodies_never_move() {
+        let cmanager = world();
+        let paddle = body(&cmanager, Vec2::new(0.0, 0.0), Vec2::new(10.0, 60.0), Shape::Aabb, LAYER_PADDLE, LAYER_WALL, true);
+        cmanager.borrow_mut().insert(paddle, CMovement2D { id: paddle, name: "paddle".to_string(), speed: 100.0, x: 0.0, y: 1.0 });
+        body(&cmanager, Vec2::new(0.0, 40.0), Vec2::new(100.0, 10.0), Shape::Aabb, LAYER_WALL, LAYER_NONE, true);
+
+        SysPhysics::new(&cmanager).execute(1.0);
+
+        assert_eq!(position(&cmanager, paddle), Vec2::new(0.0, 0.0));
+        assert!(contacts(&cmanager).is_empty());
+    }
+
+    #[test]
+    fn a_ball_passes_through_what_its_mask_leaves_out() {
+        let cmanager = world();
+        let ball = ball(&cmanager, Vec2::new(0.0, 0.0), LAYER_PADDLE, Vec2::new(0.0, 1.0), 100.0);
+        body(&cmanager, Vec2::new(0.0, 50.0), Vec2::new(100.0, 10.0), Shape::Aabb, LAYER_WALL, LAYER_NONE, true);
+
+        SysPhysics::new(&cmanager).execute(1.0);
+
+        assert_near(position(&cmanager, ball), Vec2::new(0.0, 100.0));
+        assert_eq!(direction(&cmanager, ball), Vec2::new(0.0, 1.0));
+        assert!(contacts(&cmanager).is_empty());
+    }
+
+    #[test]
+    fn a_fast_ball_bounces_off_a_thin_paddle_instead_of_tunnelling() {
+        let cmanager = world();
+        let ball = ball(&cmanager, Vec2::new(0.0, 0.0), LAYER_PADDLE, Vec2::new(1.0, 0.0), 1000.0);
+        let paddle = body(&cmanager, Vec2::new(50.0, 0.0), Vec2::new(10.0, 60.0), Shape::Aabb, LAYER_PADDLE, LAYER_NONE, true);
+
+        SysPhysics::new(&cmanager).execute(0.1);
+
+        // Touches at x = 40 after 40% of the step, backs off by the skin and spends the rest going back
+        assert_near(position(&cmanager, ball), Vec2::new(40.0 - SysPhysics::CONTACT_SKIN - 60.0, 0.0));
+        assert_eq!(direction(&cmanager, ball), Vec2::new(-1.0, 0.0));
+
+        let contacts = contacts(&cmanager);
+        assert_eq!(contacts.len(), 1);
+        assert_eq!((contacts[0].entity, contacts[0].other), (ball, paddle));
+        assert_eq!(contacts[0].normal, Vec2::new(-1.0, 0.0));
+    }
+
+    #[test]
+    fn an_overlapping_ball_heading_in_is_pushed_out_and_reflected() {
+        let cmanager = world();
+        let ball = ball(&cmanager, Vec2::new(0.0, 3.0), LAYER_WALL, Vec2::new(0.0, 1.0), 1.0);
+        let wall = body(&cmanager, Vec2::new(0.0, 10.0), Vec2::new(100.0, 10.0), Shape::Aabb, LAYER_WALL, LAYER_NONE, true);
+
+        SysPhysics::new(&cmanager).execute(1.0);
+
+        assert_near(position(&cmanager, ball), Vec2::new(0.0, -1.0));
+        assert_eq!(direction(&cmanager, ball), Vec2::new(0.0, -1.0));
+
+        let contacts = contacts(&cmanager);
+        assert_eq!(contacts.len(), 1);
+        assert_eq!((contacts[0].entity, contacts[0].other), (ball, wall));
+        assert_eq!(contacts[0].normal, Vec2::new(0.0, -1.0));
+    }
+
+    #[test]
+    fn an_overlapping_ball_heading_away_is_pushed_out_but_keeps_going() {
+        let cmanager = world();
+        let ball = ball(&cmanager, Vec2::new(0.0, 3.0), LAYER_WALL, Vec2::new(0.0, -1.0), 1.0);
+        body(&cmanager, Vec2::new(0.0, 10.0), Vec2::new(100.0, 10.0), Shape::Aabb, LAYER_WALL, LAYER_NONE, true);
+
+        SysPhysics::new(&cmanager).execute(1.0);
+
+        assert_near(position(&cmanager, ball), Vec2::new(0.0, -1.0));
+        assert_eq!(direction(&cmanager, ball), Vec2::new(0.0, -1.0));
+        assert!(contacts(&cmanager).is_empty());
+    }
+
+    #[test]
+    fn the_ai_only_changes_direction_once_its_delay_has_run_in_ticks() {
+        let id = ComponentManager::default().create_entity();
+        let position = CPosition2D::new(id, "paddle", Vec2::new(0.0, 100.0));
+        let mut paddle_info = paddle_info(id);
+
+        for _ in 0..2 {
+            SysPaddleAi::steer_paddle(&mut paddle_info, &position, Some(0.0), 0.125);
+            assert!(matches!(paddle_info.direction, Direction::Stationary));
+        }
+
+        SysPaddleAi::steer_paddle(&mut paddle_info, &position, Some(0.0), 0.125);
+        assert!(matches!(paddle_info.direction, Direction::Up));
+
+        // The delay starts over after every decision
+        SysPaddleAi::steer_paddle(&mut paddle_info, &position, Some(200.0), 0.125);
+        assert!(matches!(paddle_info.direction, Direction::Up));
+    }
+
+    // A ball just reflected off the right face of a 10x100 paddle, heading back at 45 degrees
+    fn bounce_world(speed: f32) -> (CManagerRc, Entity, Entity) {
+        let cmanager = world();
+        cmanager.borrow_mut().add_event::<PaddleHit>();
+        cmanager.borrow_mut().insert_resource(MatchConfig {
+            ball_speed_increase: 30.0,
+            max_ball_speed: 600.0,
+            max_bounce_angle: std::f32::consts::FRAC_PI_3,
+            ..MatchConfig::default()
+        });
+
+        let paddle = body(&cmanager, Vec2::new(0.0, 0.0), Vec2::new(10.0, 100.0), Shape::Aabb, LAYER_PADDLE, LAYER_NONE, true);
+        cmanager.borrow_mut().insert(paddle, paddle_info(paddle));
+
+        let ball = ball(&cmanager, Vec2::new(10.0, 0.0), LAYER_PADDLE, Vec2::new(1.0, 1.0), speed);
+        cmanager.borrow_mut().insert(ball, CBall { id: ball, name: "ball".to_string(), hits: 0 });
+
+        (cmanager, ball, paddle)
+    }
+
+    fn bounce(cmanager: &CManagerRc, ball: Entity, paddle: Entity, normal: Vec2, offset_y: f32) -> Vec<PaddleHit> {
+        SysPaddleBounce::new(cmanager).bounce(&Contact { entity: ball, other: paddle, normal, offset: Vec2::new(10.0, offset_y) });
+
+        cmanager.borrow_mut().update_events();
+        cmanager.borrow().read_events::<PaddleHit>()
+    }
+
+    fn speed(cmanager: &CManagerRc, entity: Entity) -> f32 {
+        cmanager.borrow().get::<CMovement2D>(&entity).unwrap().speed
+    }
+
+    #[test]
+    fn a_centre_hit_goes_straight_back_and_speeds_up() {
+        let (cmanager, ball, paddle) = bounce_world(500.0);
+
+        let hits = bounce(&cmanager, ball, paddle, Vec2::new(1.0, 0.0), 0.0);
+
+        assert_near(direction(&cmanager, ball), Vec2::new(2.0_f32.sqrt(), 0.0));
+        assert_eq!(speed(&cmanager, ball), 530.0);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].ball, ball);
+    }
+
+    #[test]
+    fn an_edge_hit_leaves_at_the_steepest_angle() {
+        let (cmanager, ball, paddle) = bounce_world(500.0);
+
+        // Half the paddle plus half the ball, and anything past it, is the very edge
+        bounce(&cmanager, ball, paddle, Vec2::new(1.0, 0.0), -80.0);
+
+        let angle = std::f32::consts::FRAC_PI_3;
+        assert_near(direction(&cmanager, ball), Vec2::new(angle.cos(), -angle.sin()) * 2.0_f32.sqrt());
+
+        bounce(&cmanager, ball, paddle, Vec2::new(1.0, 0.0), 27.5);
+        assert_near(direction(&cmanager, ball), Vec2::new((angle * 0.5).cos(), (angle * 0.5).sin()) * 2.0_f32.sqrt());
+    }
+
+    #[test]
+    fn speed_ups_stop_at_the_cap() {
+        let (cmanager, ball, paddle) = bounce_world(590.0);
+
+        bounce(&cmanager, ball, paddle, Vec2::new(1.0, 0.0), 0.0);
+
+        assert_eq!(speed(&cmanager, ball), 600.0);
+    }
+
+    #[test]
+    fn a_hit_on_the_end_cap_keeps_the_plain_reflection() {
+        let (cmanager, ball, paddle) = bounce_world(500.0);
+
+        let hits = bounce(&cmanager, ball, paddle, Vec2::new(0.6, -0.8), -50.0);
+
+        assert_eq!(direction(&cmanager, ball), Vec2::new(1.0, 1.0));
+        assert_eq!(speed(&cmanager, ball), 500.0);
+        assert!(hits.is_empty());
+    }
+
+    fn rally_world(config: MatchConfig) -> CManagerRc {
+        let cmanager = world();
+        cmanager.borrow_mut().add_event::<PaddleHit>();
+        cmanager.borrow_mut().add_event::<GoalScored>();
+        cmanager.borrow_mut().insert_resource(config);
+        cmanager
+    }
+
+    fn served_ball(cmanager: &CManagerRc, pos: Vec2, direction: (f32, f32), speed: f32) -> Entity {
+        let mut cmanager = cmanager.borrow_mut();
+        let id = cmanager.create_entity();
+        ball::spawn(&mut cmanager, id, pos, direction, speed);
+        id
+    }
+
+    fn balls(cmanager: &CManagerRc) -> Vec<Entity> {
+        let mut balls = Vec::new();
+        cmanager.borrow().query::<&CBall>().for_each(|entity, _| balls.push(entity));
+        balls
+    }
+
+    fn hit_paddle(cmanager: &CManagerRc, ball: Entity) {
+        cmanager.borrow().send_event(PaddleHit { ball });
+        cmanager.borrow_mut().update_events();
+
+        SysBallSplit::new(cmanager).execute(0.0);
+        cmanager.borrow_mut().apply_commands();
+    }
+
+    #[test]
+    fn a_ball_splits_every_few_hits_up_to_the_limit() {
+        let cmanager = rally_world(MatchConfig { split_every: 2, max_balls: 2, ..MatchConfig::default() });
+        let parent = served_ball(&cmanager, Vec2::new(100.0, 100.0), (1.0, 0.0), 500.0);
+
+        hit_paddle(&cmanager, parent);
+        assert_eq!(balls(&cmanager).len(), 1);
+
+        hit_paddle(&cmanager, parent);
+        let split = *balls(&cmanager).iter().find(|&&ball| ball != parent).unwrap();
+        assert_eq!(position(&cmanager, split), Vec2::new(100.0, 100.0));
+        assert_eq!(speed(&cmanager, split), 500.0);
+
+        // A dead-centre return still sends the split off on a path of its own
+        let angle = SysBallSplit::MIN_SPLIT_ANGLE;
+        assert_near(direction(&cmanager, split), Vec2::new(angle.cos(), -angle.sin()));
+
+        hit_paddle(&cmanager, parent);
+        hit_paddle(&cmanager, parent);
+        assert_eq!(balls(&cmanager).len(), 2);
+    }
+
+    #[test]
+    fn a_steep_ball_splits_into_its_mirror_image() {
+        let movement = CMovement2D { id: ComponentManager::default().create_entity(), name: "ball".to_string(), speed: 500.0, x: -1.0, y: 1.0 };
+
+        let (x, y) = SysBallSplit::split_direction(&movement);
+
+        assert_near(Vec2::new(x, y), Vec2::new(-1.0, -1.0));
+    }
+
+    #[test]
+    fn splitting_is_off_when_split_every_is_zero() {
+        let cmanager = rally_world(MatchConfig { split_every: 0, ..MatchConfig::default() });
+        let parent = served_ball(&cmanager, Vec2::new(100.0, 100.0), (1.0, 1.0), 500.0);
+
+        for _ in 0..5 {
+            hit_paddle(&cmanager, parent);
+        }
+
+        assert_eq!(balls(&cmanager), vec![parent]);
+    }
+
+    #[test]
+    fn goals_take_split_balls_out_of_play_and_serve_the_rest_again() {
+        let cmanager = rally_world(MatchConfig { ball_count: 1, ball_speed: 400.0, ..MatchConfig::default() });
+        let first = served_ball(&cmanager, Vec2::new(0.0, 100.0), (-1.0, 1.0), 700.0);
+        let second = served_ball(&cmanager, Vec2::new(1280.0, 200.0), (1.0, 1.0), 700.0);
+        if let Some(ball) = cmanager.borrow().query::<&mut CBall>().get(&second) {
+            ball.hits = 3;
+        }
+
+        cmanager.borrow().send_event(GoalScored { side: Side::Right, ball: first });
+        cmanager.borrow().send_event(GoalScored { side: Side::Left, ball: second });
+        cmanager.borrow_mut().update_events();
+
+        SysBallReset::new(&cmanager).execute(0.0);
+        cmanager.borrow_mut().apply_commands();
+
+        assert_eq!(balls(&cmanager), vec![second]);
+        assert_eq!(position(&cmanager, second), ball::START_BALL);
+        assert_eq!(speed(&cmanager, second), 400.0);
+        assert_eq!(cmanager.borrow().get::<CBall>(&second).unwrap().hits, 0);
+    }
+
+    #[test]
+    fn a_clicked_button_runs_its_callback_once() {
+        let cmanager = world();
+        cmanager.borrow_mut().add_event::<ButtonClicked>();
+
+        let presses = Rc::new(std::cell::Cell::new(0));
+        let button = cmanager.borrow_mut().create_entity();
+        let counter = Rc::clone(&presses);
+        cmanager.borrow_mut().insert(button, CButtonInfo {
+            id: button,
+            name: "button".to_string(),
+            text: "Press".to_string(),
+            callback: Some(Box::new(move || counter.set(counter.get() + 1)))
+        });
+
+        let mut system = SysButtonCallback::new(&cmanager);
+        cmanager.borrow().send_event(ButtonClicked { entity: button });
+        cmanager.borrow_mut().update_events();
+        cmanager.borrow_mut().begin_event_reads(0);
+        system.execute(0.0);
+
+        cmanager.borrow_mut().update_events();
+        cmanager.borrow_mut().begin_event_reads(0);
+        system.execute(0.0);
+
+        assert_eq!(presses.get(), 1);
+    }
+}
@@ -0,0 +1,98 @@
+/*
+    Fixed-timestep accumulator. Frame time is banked as it comes in and paid
+    out in whole ticks, so the simulation always steps by the same amount no
+    matter how fast frames are drawn. Whatever is left over in the bank is the
+    fraction of a tick the renderer should interpolate by.
+*/
+pub struct FixedTimestep {
+    tick: f32,
+    accumulator: f32
+}
+
+impl FixedTimestep {
+    // A long stall is clamped so it doesn't come back as a burst of ticks
+    const MAX_FRAME_TIME: f32 = 0.25;
+
+    pub fn new(tick_rate: u32) -> Self {
+        Self {
+            tick: 1.0 / tick_rate.max(1) as f32,
+            accumulator: 0.0
+        }
+    }
+
+    // Length of one tick in seconds, the delta time every tick runs with
+    pub fn tick(&self) -> f32 {
+        self.tick
+    }
+
+    pub fn accumulate(&mut self, delta_time: f32) {
+        self.accumulator += delta_time.min(Self::MAX_FRAME_TIME);
+    }
+
+    // True while a whole tick is banked, spending it
+    pub fn consume_tick(&mut self) -> bool {
+        if self.accumulator < self.tick {
+            return false;
+        }
+
+        self.accumulator -= self.tick;
+        true
+    }
+
+    // How far between the last two ticks the current frame falls, from 0 to 1
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.tick).clamp(0.0, 1.0)
+    }
+
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticks(timestep: &mut FixedTimestep) -> usize {
+        std::iter::from_fn(|| timestep.consume_tick().then_some(())).count()
+    }
+
+    #[test]
+    fn frame_time_is_paid_out_in_whole_ticks() {
+        let mut timestep = FixedTimestep::new(8);
+
+        timestep.accumulate(0.0625);
+        assert_eq!(ticks(&mut timestep), 0);
+
+        timestep.accumulate(0.25);
+        assert_eq!(ticks(&mut timestep), 2);
+        assert_eq!(timestep.alpha(), 0.5);
+    }
+
+    #[test]
+    fn a_long_stall_is_clamped() {
+        let mut timestep = FixedTimestep::new(8);
+
+        timestep.accumulate(10.0);
+        assert_eq!(ticks(&mut timestep), 2);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+
+    #[test]
+    fn alpha_stays_between_zero_and_one() {
+        let mut timestep = FixedTimestep::new(10);
+        assert_eq!(timestep.alpha(), 0.0);
+
+        timestep.accumulate(0.25);
+        assert_eq!(timestep.alpha(), 1.0);
+
+        timestep.reset();
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+
+    #[test]
+    fn a_zero_tick_rate_runs_at_one_tick_per_second() {
+        let timestep = FixedTimestep::new(0);
+        assert_eq!(timestep.tick(), 1.0);
+    }
+}
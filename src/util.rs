@@ -1,9 +1,14 @@
-use crate::{GAME_STATE, PLAY};
 use crate::componentmanager::ComponentManager;
+use crate::controllers::Controllers;
 use crate::fontmanager::FontManager;
-use crate::gamestate::GameStates;
+use crate::gamestate::Transition;
+use crate::input::{ActionMap, HeldInput};
+use crate::resources::{GameRng, MatchSetup, MatchSummary};
+use crate::vec2::Vec2;
+use rand::Rng;
 use sdl2::event::Event;
 use sdl2::EventPump;
+use sdl2::rect::{Point, Rect};
 use sdl2::render::Canvas;
 use sdl2::video::Window;
 use std::cell::RefCell;
@@ -13,8 +18,9 @@ pub type CanvasRc = Rc<RefCell<Canvas<Window>>>;
 pub type CManagerRc = Rc<RefCell<ComponentManager>>;
 pub type EventRc = Rc<RefCell<EventRunner>>;
 pub type FontRc<'ttf> = Rc<RefCell<FontManager<'ttf>>>;
-pub type GameStateRc = Rc<RefCell<GameStates>>;
-pub type PlayRc = Rc<RefCell<bool>>;
+pub type TransitionRc = Rc<RefCell<Vec<Transition>>>;
+pub type SummaryRc = Rc<RefCell<Option<MatchSummary>>>;
+pub type MatchSetupRc = Rc<RefCell<MatchSetup>>;
 
 // Shorthand for initializing Rc<RefCell<T>> pattern
 pub fn rcf<T>(value: T) -> Rc<RefCell<T>> {
@@ -25,13 +31,13 @@ pub fn create_component_manager() -> CManagerRc {
     rcf(ComponentManager::default())
 }
 
-pub fn random_direction() -> (f32, f32) {
-    let x = match rand::random() {
+pub fn random_direction<R: Rng>(rng: &mut R) -> (f32, f32) {
+    let x = match rng.gen() {
         true => 1.0,
         false => -1.0
     };
 
-    let y = match rand::random() {
+    let y = match rng.gen() {
         true => 1.0,
         false => -1.0
     };
@@ -39,25 +45,38 @@ pub fn random_direction() -> (f32, f32) {
     (x, y)
 }
 
-pub fn get_allocated_ids(cmanage: &CManagerRc) -> Vec<usize> {
-    cmanage.borrow().id_allocator.get_allocated_ids()
+// Draws from the world's GameRng, or the thread's RNG outside of a match
+pub fn world_random_direction(cmanager: &ComponentManager) -> (f32, f32) {
+    match cmanager.resource_mut::<GameRng>() {
+        Some(mut rng) => random_direction(&mut rng.0),
+        None => random_direction(&mut rand::thread_rng())
+    }
 }
 
-pub fn change_gamestate(new_state: GameStates) {
-    unsafe {
-        GAME_STATE = new_state;
-    }
+// Rounds a simulation position to the pixel it gets drawn at
+pub fn to_point(pos: Vec2) -> Point {
+    Point::new(pos.x.round() as i32, pos.y.round() as i32)
 }
 
-pub fn set_play(play: bool) {
-    unsafe {
-        PLAY = play;
-    }
+pub fn to_vec2(point: Point) -> Vec2 {
+    Vec2::new(point.x as f32, point.y as f32)
+}
+
+pub fn centered_rect(center: Vec2, size: Vec2) -> Rect {
+    Rect::from_center(to_point(center), size.x.round() as u32, size.y.round() as u32)
+}
+
+// Queues a state change, applied by the main loop before the next frame
+pub fn request_transition(transitions: &TransitionRc, transition: Transition) {
+    transitions.borrow_mut().push(transition);
 }
 
 pub struct EventRunner {
     pub event_pump: EventPump,
-    pub event_list: Vec<Event>
+    pub event_list: Vec<Event>,
+    pub held: HeldInput,
+    pub actions: ActionMap,
+    pub controllers: Controllers
 }
 
 impl EventRunner {
@@ -66,5 +85,9 @@ impl EventRunner {
         for event in self.event_pump.poll_iter() {
             self.event_list.push(event);
         }
+
+        self.controllers.update(&self.event_list);
+        self.held.update(&self.event_list);
+        self.actions.update(&self.event_list, &self.held);
     }
 }
\ No newline at end of file
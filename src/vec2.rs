@@ -0,0 +1,86 @@
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+/*
+    Plain 2D vector for sub-pixel positions and sizes. The simulation works
+    entirely in these and only rounds to SDL's integer Points and Rects when
+    something is drawn.
+*/
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Vec2 {
+    pub x: f32,
+    pub y: f32
+}
+
+impl Vec2 {
+    pub const ZERO: Vec2 = Vec2::new(0.0, 0.0);
+
+    pub const fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+
+    pub fn length(self) -> f32 {
+        self.x.hypot(self.y)
+    }
+
+    // Zero stays zero rather than turning into NaNs
+    pub fn normalized(self) -> Self {
+        let length = self.length();
+        if length == 0.0 {
+            return Self::ZERO;
+        }
+
+        self * (1.0 / length)
+    }
+
+    pub fn dot(self, other: Self) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    pub fn lerp(self, other: Self, alpha: f32) -> Self {
+        self + (other - self) * alpha
+    }
+}
+
+impl Add for Vec2 {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl AddAssign for Vec2 {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Vec2 {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl SubAssign for Vec2 {
+    fn sub_assign(&mut self, other: Self) {
+        *self = *self - other;
+    }
+}
+
+impl Mul<f32> for Vec2 {
+    type Output = Self;
+
+    fn mul(self, scale: f32) -> Self {
+        Self::new(self.x * scale, self.y * scale)
+    }
+}
+
+impl Neg for Vec2 {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.x, -self.y)
+    }
+}
@@ -0,0 +1,37 @@
+use crate::collision::Shape;
+use crate::components::{CCollision2D, CPosition2D, LAYER_NONE, LAYER_WALL};
+use crate::util::CManagerRc;
+use crate::vec2::Vec2;
+
+// How far each wall reaches out of view, deep enough that nothing passes through
+const THICKNESS: f32 = 100.0;
+
+// Invisible static walls just past the top and bottom edges of the window
+pub fn create_bounds(cmanager: &CManagerRc, width: u32, height: u32) {
+    let (width, height) = (width as f32, height as f32);
+    // Wider than the window so balls heading into a goal still bounce
+    let size = Vec2::new(width * 2.0, THICKNESS);
+
+    create(cmanager, "wall_top", Vec2::new(width / 2.0, -THICKNESS / 2.0), size);
+    create(cmanager, "wall_bottom", Vec2::new(width / 2.0, height + THICKNESS / 2.0), size);
+}
+
+pub fn create(cmanager: &CManagerRc, name: &str, pos: Vec2, size: Vec2) {
+    let mut cmanager = cmanager.borrow_mut();
+    let id = cmanager.create_entity();
+
+    let collision_2d = CCollision2D {
+        id,
+        name: name.to_string(),
+        size,
+        shape: Shape::Aabb,
+        layer: LAYER_WALL,
+        mask: LAYER_NONE,
+        is_static: true
+    };
+
+    let position_2d = CPosition2D::new(id, name, pos);
+
+    cmanager.insert(id, collision_2d);
+    cmanager.insert(id, position_2d);
+}